@@ -0,0 +1,110 @@
+//! Compares sequential and parallel translation of a large generated multi-file program.
+//!
+//! Run with `cargo bench --bench parallel`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process,
+    time::{Duration, Instant},
+};
+
+use vm_translator::{backend::default_backend, parallel::default_jobs, program::translate_files};
+
+const FILES: usize = 64;
+const COMMANDS_PER_FILE: usize = 20_000;
+const RUNS: usize = 3;
+
+fn main() {
+    let dir = std::env::temp_dir().join(format!("vm_translator_bench_{}", process::id()));
+    let files = generate_program(&dir);
+
+    let mut job_counts = vec![2, 4, default_jobs()];
+    job_counts.retain(|jobs| *jobs > 1);
+    job_counts.sort();
+    job_counts.dedup();
+
+    println!(
+        "{} files x {} commands, best of {} runs on {} CPU(s)",
+        FILES,
+        COMMANDS_PER_FILE,
+        RUNS,
+        default_jobs()
+    );
+    let (sequential, expected) = best_of(&files, 1);
+    println!("   1 job(s): {:>8.1?}", sequential);
+    for jobs in job_counts {
+        let (elapsed, output) = best_of(&files, jobs);
+        assert_eq!(
+            output, expected,
+            "output with {} jobs must match 1 job",
+            jobs
+        );
+        println!(
+            "  {:>2} job(s): {:>8.1?}  speedup {:.2}x",
+            jobs,
+            elapsed,
+            sequential.as_secs_f64() / elapsed.as_secs_f64()
+        );
+    }
+
+    fs::remove_dir_all(&dir).expect("failed to clean up generated program");
+}
+
+/// Writes `FILES` files that together exercise every command and segment, returning their
+/// paths in translation order.
+fn generate_program(dir: &Path) -> Vec<PathBuf> {
+    let body = [
+        "push constant 17",
+        "push constant 17",
+        "eq",
+        "push local 2",
+        "push argument 1",
+        "gt",
+        "pop that 3",
+        "push static 4",
+        "push temp 5",
+        "lt",
+        "pop this 1",
+        "push pointer 0",
+        "neg",
+        "not",
+        "push constant 9",
+        "and",
+        "push constant 3",
+        "or",
+        "push constant 1",
+        "sub",
+        "pop local 0",
+    ];
+
+    fs::create_dir_all(dir).expect("failed to create benchmark directory");
+    (0..FILES)
+        .map(|index| {
+            let source: String = body
+                .iter()
+                .cycle()
+                .take(COMMANDS_PER_FILE)
+                .flat_map(|line| [*line, "\n"])
+                .collect();
+            let path = dir.join(format!("File{:03}.vm", index));
+            fs::write(&path, source).expect("failed to write generated file");
+            path
+        })
+        .collect()
+}
+
+fn best_of(files: &[PathBuf], jobs: usize) -> (Duration, Vec<String>) {
+    let mut best = Duration::MAX;
+    let mut output = Vec::new();
+
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        output = translate_files(files, default_backend(), jobs)
+            .into_iter()
+            .map(|fragment| fragment.expect("generated program should translate"))
+            .collect();
+        best = best.min(start.elapsed());
+    }
+    (best, output)
+}
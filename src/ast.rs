@@ -0,0 +1,133 @@
+//! The JSON document written by `translate --emit ast`, for tools that want to see what the
+//! translator parsed without parsing VM code themselves. The layout is described in the
+//! README and versioned by [`AST_SCHEMA_VERSION`].
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::{
+    command::{Command, Segment},
+    program::Source,
+};
+
+/// Bumped whenever a field is removed, renamed or changes meaning. Adding a field does not
+/// change the version, so consumers should ignore fields they do not know.
+pub const AST_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize)]
+pub struct Ast {
+    pub schema_version: u32,
+    pub files: Vec<AstFile>,
+}
+
+/// The commands of one source file, in source order.
+#[derive(Debug, Serialize)]
+pub struct AstFile {
+    // As given on the command line, or - for stdin
+    pub path: String,
+    // Name static variables are scoped under
+    pub name: String,
+    pub commands: Vec<AstCommand>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AstCommand {
+    // 1-based line number in the source file
+    pub line: usize,
+    pub command: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub segment: Option<Segment>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<u16>,
+    // The command in canonical VM syntax
+    pub text: String,
+}
+
+impl AstCommand {
+    pub fn new(command: &Command, line: usize) -> Self {
+        let (segment, index) = match command {
+            Command::Push { segment, index } | Command::Pop { segment, index } => {
+                (Some(*segment), Some(*index))
+            }
+            _ => (None, None),
+        };
+        Self {
+            line,
+            command: command.name(),
+            segment,
+            index,
+            text: command.to_string(),
+        }
+    }
+}
+
+impl Ast {
+    /// Parses every source. Stops at the first command that does not parse.
+    pub fn parse(sources: Vec<Source>) -> Result<Self> {
+        let mut files = Vec::new();
+        for source in sources {
+            let mut commands = Vec::new();
+            for result in source.lexer {
+                let lexed_res = result.with_context(|| source.display_name.clone())?;
+                if let Some(command) = lexed_res.command {
+                    commands.push(AstCommand::new(&command, lexed_res.line));
+                }
+            }
+            files.push(AstFile {
+                path: source.display_name,
+                name: source.file_name,
+                commands,
+            });
+        }
+
+        Ok(Self {
+            schema_version: AST_SCHEMA_VERSION,
+            files,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+
+    use super::*;
+
+    fn parse(content: &str) -> Result<Ast> {
+        Ast::parse(vec![Source::from_bytes(
+            Path::new("dir/Main.vm"),
+            content.as_bytes().to_vec(),
+        )?])
+    }
+
+    #[test]
+    fn test_ast_layout() {
+        let ast = parse("// comment\npush constant 7\n\npop static 2 // trailing\nadd\n").unwrap();
+        assert_eq!(
+            serde_json::to_value(&ast).unwrap(),
+            json!({
+                "schema_version": 1,
+                "files": [{
+                    "path": "dir/Main.vm",
+                    "name": "Main",
+                    "commands": [
+                        {"line": 2, "command": "push", "segment": "constant", "index": 7, "text": "push constant 7"},
+                        {"line": 4, "command": "pop", "segment": "static", "index": 2, "text": "pop static 2"},
+                        {"line": 5, "command": "add", "text": "add"},
+                    ],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn test_ast_reports_parse_errors() {
+        let err = parse("push constant 7\npush nowhere 1\n").unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "dir/Main.vm: line 2: Unknown segment: nowhere"
+        );
+    }
+}
@@ -0,0 +1,260 @@
+use anyhow::{Result, anyhow};
+
+use crate::{
+    backend::{Backend, SegmentAddress, c_string, segment_address},
+    command::{Command, Segment},
+    converter::Converter,
+    memory_layout::MemoryLayout,
+};
+
+/// A self-contained C program that models the Hack RAM as an array, for running VM programs
+/// natively. The program takes `ADDRESS=VALUE` arguments to initialise RAM, and prints every
+/// non-zero RAM cell and static variable when it finishes.
+pub struct CBackend;
+
+impl Backend for CBackend {
+    fn name(&self) -> &'static str {
+        "c"
+    }
+
+    fn description(&self) -> &'static str {
+        "C source that runs the program natively and dumps its RAM"
+    }
+
+    fn extension(&self) -> &'static str {
+        "c"
+    }
+
+    fn converter(&self, file_name: &str) -> Box<dyn Converter> {
+        Box::new(CConverter {
+            file_name: file_name.to_string(),
+        })
+    }
+
+    fn prologue(&self) -> String {
+        PROLOGUE.to_string()
+    }
+
+    fn epilogue(&self) -> String {
+        EPILOGUE.to_string()
+    }
+}
+
+// Arithmetic relies on conversion to int16_t wrapping around, which every mainstream
+// compiler does and C23 guarantees
+const PROLOGUE: &str = r#"/* Generated by vm_translator */
+#include <stdint.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define RAM_SIZE 32768
+#define SP 0
+#define STATIC_BASE 16
+#define STATIC_LIMIT 256
+#define STACK_BASE 256
+
+static int16_t ram[RAM_SIZE];
+static const char *static_names[STATIC_LIMIT - STATIC_BASE];
+static int static_count;
+
+static void fail(const char *message) {
+    fprintf(stderr, "Error: %s\n", message);
+    exit(1);
+}
+
+static inline void push(int16_t value) {
+    uint16_t sp = (uint16_t)ram[SP];
+    if (sp >= RAM_SIZE) {
+        fail("Stack overflow");
+    }
+    ram[sp] = value;
+    ram[SP] = (int16_t)(sp + 1);
+}
+
+static inline int16_t pop(void) {
+    uint16_t sp = (uint16_t)ram[SP];
+    if (sp <= STACK_BASE) {
+        fail("Stack underflow");
+    }
+    ram[SP] = (int16_t)(sp - 1);
+    return ram[sp - 1];
+}
+
+static inline void pop_to(uint16_t address) {
+    ram[address] = pop();
+}
+
+static inline int16_t truth(int value) {
+    return value ? -1 : 0;
+}
+
+/* Address of entry `index` of the segment whose base is stored at `pointer` */
+static inline uint16_t segment(int pointer, uint16_t index) {
+    uint32_t address = (uint32_t)(uint16_t)ram[pointer] + index;
+    if (address >= RAM_SIZE) {
+        fail("Segment access outside RAM");
+    }
+    return (uint16_t)address;
+}
+
+/* Static variables get the next free address the first time they are used */
+static inline int16_t *static_slot(const char *name) {
+    int i;
+    for (i = 0; i < static_count; i++) {
+        if (strcmp(static_names[i], name) == 0) {
+            return &ram[STATIC_BASE + i];
+        }
+    }
+    if (STATIC_BASE + static_count >= STATIC_LIMIT) {
+        fail("Too many static variables");
+    }
+    static_names[static_count] = name;
+    return &ram[STATIC_BASE + static_count++];
+}
+
+static void dump(void) {
+    int i;
+    for (i = 0; i < RAM_SIZE; i++) {
+        if (ram[i] != 0) {
+            printf("ram %d %d\n", i, ram[i]);
+        }
+    }
+    for (i = 0; i < static_count; i++) {
+        printf("static %s %d\n", static_names[i], ram[STATIC_BASE + i]);
+    }
+}
+
+int main(int argc, char **argv) {
+    int i;
+    ram[SP] = STACK_BASE;
+    for (i = 1; i < argc; i++) {
+        char *value;
+        long address = strtol(argv[i], &value, 10);
+        if (*value != '=' || address < 0 || address >= RAM_SIZE) {
+            fprintf(stderr, "Invalid assignment %s, expected ADDRESS=VALUE\n", argv[i]);
+            return 2;
+        }
+        ram[address] = (int16_t)strtol(value + 1, NULL, 10);
+    }
+
+"#;
+
+const EPILOGUE: &str = "
+    dump();
+    return 0;
+}
+";
+
+struct CConverter {
+    // Scopes static variables, so files can be concatenated without their statics colliding
+    file_name: String,
+}
+
+impl CConverter {
+    fn address(&self, segment: Segment, index: u16) -> Result<String> {
+        let address = match segment_address(&MemoryLayout::STANDARD, segment, index)? {
+            SegmentAddress::Based { pointer, index } => format!("segment({}, {})", pointer, index),
+            SegmentAddress::Fixed(address) => address.to_string(),
+            SegmentAddress::Static(_) => unreachable!("static has no fixed address"),
+        };
+        Ok(address)
+    }
+
+    fn static_slot(&self, index: u16) -> String {
+        let name = c_string(&format!("{}.{}", self.file_name, index));
+        format!(
+            "static int16_t *slot; if (!slot) slot = static_slot({});",
+            name
+        )
+    }
+}
+
+impl Converter for CConverter {
+    fn convert(&mut self, command: Command) -> Result<String> {
+        let code = match command {
+            Command::Push {
+                segment: Segment::Constant,
+                index,
+            } => format!("push({});", index as i16),
+            Command::Push {
+                segment: Segment::Static,
+                index,
+            } => format!("{{ {} push(*slot); }}", self.static_slot(index)),
+            Command::Push { segment, index } => {
+                format!("push(ram[{}]);", self.address(segment, index)?)
+            }
+            Command::Pop {
+                segment: Segment::Constant,
+                ..
+            } => return Err(anyhow!("Cannot pop constant")),
+            Command::Pop {
+                segment: Segment::Static,
+                index,
+            } => format!("{{ {} *slot = pop(); }}", self.static_slot(index)),
+            Command::Pop { segment, index } => {
+                format!("pop_to({});", self.address(segment, index)?)
+            }
+            Command::Add => binary("x + y"),
+            Command::Sub => binary("x - y"),
+            Command::And => binary("x & y"),
+            Command::Or => binary("x | y"),
+            Command::Eq => binary("truth(x == y)"),
+            Command::Gt => binary("truth(x > y)"),
+            Command::Lt => binary("truth(x < y)"),
+            Command::Neg => "push((int16_t)-pop());".to_string(),
+            Command::Not => "push((int16_t)~pop());".to_string(),
+        };
+
+        Ok(format!("    // {}\n    {}", command, code))
+    }
+}
+
+// The right operand is on top of the stack, so it is popped first
+fn binary(expression: &str) -> String {
+    format!(
+        "{{ int16_t y = pop(); int16_t x = pop(); push((int16_t)({})); }}",
+        expression
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(input: &str) -> Result<String> {
+        CBackend
+            .converter("Main")
+            .convert(Command::from(input).unwrap())
+    }
+
+    #[test]
+    fn test_commands_become_statements() {
+        assert_eq!(
+            convert("push constant 7").unwrap(),
+            "    // push constant 7\n    push(7);"
+        );
+        assert_eq!(
+            convert("pop local 2").unwrap(),
+            "    // pop local 2\n    pop_to(segment(1, 2));"
+        );
+        assert!(
+            convert("push static 3")
+                .unwrap()
+                .contains("static_slot(\"Main.3\")")
+        );
+    }
+
+    #[test]
+    fn test_invalid_temp_index_is_rejected() {
+        assert!(convert("push temp 7").is_ok());
+        assert!(convert("push temp 8").is_err());
+    }
+
+    #[test]
+    fn test_c_string_escapes() {
+        assert_eq!(c_string("Main.1"), "\"Main.1\"");
+        assert_eq!(c_string("a\"b\\c"), "\"a\\\"b\\\\c\"");
+        assert_eq!(c_string("é"), "\"\\303\\251\"");
+    }
+}
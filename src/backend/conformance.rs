@@ -0,0 +1,383 @@
+//! Conformance suite that every backend in the registry must pass. Each backend is run over
+//! the programs in `test_programs/`, and backends whose output can be executed here are
+//! checked against the interpreter.
+
+use std::{collections::BTreeMap, fs, path::Path, process};
+
+use anyhow::{Context, Result, bail};
+
+use crate::{
+    backend::{Backend, backends, hack_cpu, mangle, rv32i_cpu},
+    command::Command,
+    golden::test_programs,
+    interpreter::{Interpreter, RAM_SIZE},
+    program::translate_file,
+    test_util::TempDir,
+};
+
+// Segment pointers the course test scripts start programs with
+const INITIAL_POINTERS: [(usize, i16); 5] = [(0, 256), (1, 300), (2, 400), (3, 3000), (4, 3010)];
+
+/// The state a program leaves behind, in a form every backend can produce.
+struct Machine {
+    ram: Vec<i16>,
+    statics: BTreeMap<String, i16>,
+}
+
+/// Runs a backend's output for a program translated from `file_name`, starting from the given
+/// RAM.
+type Executor = fn(&str, &str, &[i16]) -> Result<Machine>;
+
+struct Harness {
+    backend: &'static str,
+    // `None` for backends whose output cannot be executed without external tools
+    execute: Option<Executor>,
+    // Why `execute` cannot run on this machine, if it cannot
+    unavailable: fn() -> Option<String>,
+}
+
+static HARNESSES: &[Harness] = &[
+    Harness {
+        backend: "hack",
+        execute: Some(execute_hack),
+        unavailable: always_available,
+    },
+    Harness {
+        backend: "c",
+        execute: Some(execute_c),
+        unavailable: needs_cc,
+    },
+    Harness {
+        backend: "x86-64",
+        execute: Some(execute_x86_64),
+        unavailable: needs_x86_64,
+    },
+    // Needs a WebAssembly runtime, see the structure checks in `wasm.rs` instead
+    Harness {
+        backend: "wasm",
+        execute: None,
+        unavailable: always_available,
+    },
+    Harness {
+        backend: "llvm",
+        execute: Some(execute_llvm),
+        unavailable: needs_llc,
+    },
+    Harness {
+        backend: "rv32i",
+        execute: Some(execute_rv32i),
+        unavailable: always_available,
+    },
+];
+
+fn always_available() -> Option<String> {
+    None
+}
+
+// The C compiler driver that builds native output
+fn compiler() -> String {
+    std::env::var("CC").unwrap_or_else(|_| "cc".to_string())
+}
+
+fn missing_tool(tool: &str) -> Option<String> {
+    let found = process::Command::new(tool)
+        .arg("--version")
+        .output()
+        .is_ok();
+    (!found).then(|| format!("{} was not found", tool))
+}
+
+fn needs_cc() -> Option<String> {
+    missing_tool(&compiler())
+}
+
+fn needs_x86_64() -> Option<String> {
+    if !cfg!(target_arch = "x86_64") {
+        return Some("the host is not x86-64".to_string());
+    }
+    needs_cc()
+}
+
+fn needs_llc() -> Option<String> {
+    missing_tool("llc").or_else(needs_cc)
+}
+
+fn execute_hack(output: &str, file_name: &str, ram: &[i16]) -> Result<Machine> {
+    let program = hack_cpu::assemble(output)?;
+    let mut ram = ram.to_vec();
+    hack_cpu::execute(&program, &mut ram)?;
+
+    // Converter scratch variables are also symbols, so only keep the file's statics
+    let prefix = format!("{}.", file_name);
+    let statics = program
+        .symbols
+        .iter()
+        .filter(|(name, _)| {
+            name.strip_prefix(&prefix)
+                .is_some_and(|index| index.parse::<u16>().is_ok())
+        })
+        .map(|(name, address)| (name.clone(), ram[*address as usize]))
+        .collect();
+    Ok(Machine { ram, statics })
+}
+
+fn execute_c(output: &str, file_name: &str, ram: &[i16]) -> Result<Machine> {
+    let flags = ["-std=c99", "-Wall", "-Wextra", "-pedantic", "-Werror"];
+    execute_native(output, &format!("{}.c", file_name), &flags, ram)
+}
+
+fn execute_x86_64(output: &str, file_name: &str, ram: &[i16]) -> Result<Machine> {
+    execute_native(output, &format!("{}.s", file_name), &[], ram)
+}
+
+fn execute_rv32i(output: &str, file_name: &str, ram: &[i16]) -> Result<Machine> {
+    let mut program = rv32i_cpu::assemble(output)?;
+    let base = program.symbols["vm_ram"];
+    for (address, value) in ram.iter().enumerate() {
+        program.write_half(base + 2 * address as u32, *value);
+    }
+    program.call("vm_run")?;
+
+    let ram = (0..RAM_SIZE)
+        .map(|address| program.read_half(base + 2 * address as u32))
+        .collect();
+    let prefix = format!("vm_static.{}.", mangle(file_name));
+    let statics = program
+        .symbols
+        .iter()
+        .filter_map(|(symbol, address)| {
+            let index = symbol.strip_prefix(&prefix)?;
+            Some((
+                format!("{}.{}", file_name, index),
+                program.read_half(*address),
+            ))
+        })
+        .collect();
+    Ok(Machine { ram, statics })
+}
+
+/// Compiles the IR to assembly with `llc`, then builds that like the x86-64 output.
+fn execute_llvm(output: &str, file_name: &str, ram: &[i16]) -> Result<Machine> {
+    let dir = TempDir::new(&format!("conformance_{}.ll", file_name));
+    let source = dir.0.join(format!("{}.ll", file_name));
+    fs::write(&source, output)?;
+
+    // LLVM 14 only accepts opaque pointers behind a flag that later versions removed
+    let mut failure = String::new();
+    for flags in [&[][..], &["-opaque-pointers"][..]] {
+        let compile = process::Command::new("llc")
+            .args(flags)
+            .args(["-relocation-model=pic", "-o", "-"])
+            .arg(&source)
+            .output()
+            .context("failed to run llc")?;
+        if compile.status.success() {
+            let assembly = String::from_utf8(compile.stdout)?;
+            return execute_native(&assembly, &format!("{}.s", file_name), &[], ram);
+        }
+        failure = String::from_utf8_lossy(&compile.stderr).into_owned();
+    }
+    bail!("llc failed:\n{}", failure)
+}
+
+/// Builds `output` with the system C compiler driver (`$CC`, or `cc`), runs it with `ram` as
+/// `ADDRESS=VALUE` arguments and parses the RAM dump it prints.
+fn execute_native(output: &str, source_name: &str, flags: &[&str], ram: &[i16]) -> Result<Machine> {
+    let dir = TempDir::new(&format!("conformance_{}", source_name));
+    let source = dir.0.join(source_name);
+    let binary = dir.0.join("program");
+    fs::write(&source, output)?;
+
+    let compiler = compiler();
+    let build = process::Command::new(&compiler)
+        .args(flags)
+        .arg("-o")
+        .arg(&binary)
+        .arg(&source)
+        .output()
+        .with_context(|| format!("failed to run {}", compiler))?;
+    if !build.status.success() {
+        bail!(
+            "{} failed:\n{}",
+            compiler,
+            String::from_utf8_lossy(&build.stderr)
+        );
+    }
+    let assignments = ram
+        .iter()
+        .enumerate()
+        .filter(|(_, value)| **value != 0)
+        .map(|(address, value)| format!("{}={}", address, value));
+    let run = process::Command::new(&binary).args(assignments).output()?;
+    if !run.status.success() {
+        bail!("program failed:\n{}", String::from_utf8_lossy(&run.stderr));
+    }
+
+    let mut machine = Machine {
+        ram: vec![0; RAM_SIZE],
+        statics: BTreeMap::new(),
+    };
+    for line in String::from_utf8(run.stdout)?.lines() {
+        match line.split(' ').collect::<Vec<_>>()[..] {
+            ["ram", address, value] => machine.ram[address.parse::<usize>()?] = value.parse()?,
+            ["static", name, value] => {
+                machine.statics.insert(name.to_string(), value.parse()?);
+            }
+            _ => bail!("unexpected output line: {}", line),
+        }
+    }
+    Ok(machine)
+}
+
+fn harness(backend: &dyn Backend) -> &'static Harness {
+    HARNESSES
+        .iter()
+        .find(|harness| harness.backend == backend.name())
+        .unwrap_or_else(|| panic!("backend {} has no conformance harness", backend.name()))
+}
+
+fn translate_program(backend: &dyn Backend, path: &Path) -> Result<String> {
+    Ok(format!(
+        "{}{}{}",
+        backend.prologue(),
+        translate_file(path, backend)?,
+        backend.epilogue()
+    ))
+}
+
+fn initial_ram() -> Vec<i16> {
+    let mut ram = vec![0; RAM_SIZE];
+    for (address, value) in INITIAL_POINTERS {
+        ram[address] = value;
+    }
+    ram
+}
+
+fn interpret(path: &Path) -> Machine {
+    let source = fs::read_to_string(path).unwrap();
+    let file_name = path.file_stem().unwrap().to_str().unwrap();
+    let mut interpreter = Interpreter::new();
+    for (address, value) in INITIAL_POINTERS {
+        interpreter.set(address, value).unwrap();
+    }
+
+    for line in source.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        let command = Command::from(line).unwrap();
+        interpreter.execute(file_name, &command).unwrap();
+    }
+
+    Machine {
+        ram: interpreter.ram().to_vec(),
+        statics: interpreter.statics(),
+    }
+}
+
+/// Addresses whose contents are defined by the VM semantics: the pointers, temp, the live
+/// stack and the start of every segment a pointer refers to.
+fn observable_addresses(ram: &[i16]) -> Vec<usize> {
+    let mut addresses: Vec<usize> = (0..13).collect();
+    addresses.extend(256..ram[0] as u16 as usize);
+    for pointer in &ram[1..=4] {
+        let base = *pointer as u16 as usize;
+        if base >= 256 {
+            addresses.extend((base..base + 16).filter(|address| *address < RAM_SIZE));
+        }
+    }
+    addresses.sort();
+    addresses.dedup();
+    addresses
+}
+
+#[test]
+fn test_every_backend_has_a_harness() {
+    for backend in backends() {
+        harness(*backend);
+    }
+}
+
+#[test]
+fn test_programs_translate_deterministically() {
+    for backend in backends() {
+        for program in test_programs() {
+            let first = translate_program(*backend, &program)
+                .unwrap_or_else(|e| panic!("{} failed on {:?}: {:#}", backend.name(), program, e));
+            let second = translate_program(*backend, &program).unwrap();
+            assert_eq!(
+                first,
+                second,
+                "{} output for {:?} is not deterministic",
+                backend.name(),
+                program
+            );
+        }
+    }
+}
+
+#[test]
+fn test_invalid_commands_are_rejected() {
+    let invalid = [
+        "pop constant 0",
+        "push pointer 2",
+        "pop pointer 5",
+        "push constant 40000",
+    ];
+
+    for backend in backends() {
+        for input in invalid {
+            let mut converter = backend.converter("Test");
+            // Rejected either when parsed or when converted, but never translated
+            let converted = Command::from(input).and_then(|command| converter.convert(command));
+            assert!(converted.is_err(), "{} accepted: {}", backend.name(), input);
+        }
+    }
+}
+
+#[test]
+fn test_programs_match_interpreter() {
+    for backend in backends() {
+        let harness = harness(*backend);
+        let Some(execute) = harness.execute else {
+            continue;
+        };
+        // Only this backend's check is skipped, the others still run
+        if let Some(reason) = (harness.unavailable)() {
+            eprintln!("skipping the {} output check: {}", backend.name(), reason);
+            continue;
+        }
+
+        for program in test_programs() {
+            let expected = interpret(&program);
+            let output = translate_program(*backend, &program).unwrap();
+            let file_name = program.file_stem().unwrap().to_str().unwrap();
+            let actual = execute(&output, file_name, &initial_ram()).unwrap_or_else(|e| {
+                panic!(
+                    "{} output for {:?} failed: {:#}",
+                    backend.name(),
+                    program,
+                    e
+                )
+            });
+
+            for address in observable_addresses(&expected.ram) {
+                assert_eq!(
+                    actual.ram[address],
+                    expected.ram[address],
+                    "{} on {:?}: RAM[{}] differs",
+                    backend.name(),
+                    program,
+                    address
+                );
+            }
+            assert_eq!(
+                actual.statics,
+                expected.statics,
+                "{} on {:?}: statics differ",
+                backend.name(),
+                program
+            );
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    backend::Backend,
+    converter::{Converter, HackConverter},
+    labels::LabelScheme,
+    memory_layout::MemoryLayout,
+};
+
+/// The original target: assembly for the Hack computer from Nand to Tetris, or for a
+/// modified Hack machine with a different memory layout.
+pub struct HackBackend {
+    layout: MemoryLayout,
+    labels: LabelScheme,
+}
+
+impl HackBackend {
+    pub const STANDARD: HackBackend =
+        HackBackend::new(MemoryLayout::STANDARD, LabelScheme::DEFAULT);
+
+    pub const fn new(layout: MemoryLayout, labels: LabelScheme) -> Self {
+        Self { layout, labels }
+    }
+}
+
+impl Backend for HackBackend {
+    fn name(&self) -> &'static str {
+        "hack"
+    }
+
+    fn description(&self) -> &'static str {
+        "Hack assembly for the Nand to Tetris computer"
+    }
+
+    fn extension(&self) -> &'static str {
+        "asm"
+    }
+
+    fn converter(&self, file_name: &str) -> Box<dyn Converter> {
+        Box::new(HackConverter::with_options(
+            file_name.to_string(),
+            self.layout,
+            self.labels.clone(),
+        ))
+    }
+
+    fn prologue(&self) -> String {
+        if self.layout.stack_base == MemoryLayout::STANDARD.stack_base {
+            return String::new();
+        }
+        format!(
+            "// set up the stack\n@{}\nD=A\n@{}\nM=D\n\n",
+            self.layout.stack_base,
+            self.layout.register("SP")
+        )
+    }
+
+    fn fingerprint(&self) -> String {
+        if self.layout == MemoryLayout::STANDARD && self.labels == LabelScheme::DEFAULT {
+            String::new()
+        } else {
+            format!("{:?} {}", self.layout, self.labels)
+        }
+    }
+
+    fn defined_labels(&self, fragment: &str) -> Vec<String> {
+        fragment
+            .lines()
+            .filter_map(|line| {
+                let code = line.split("//").next().unwrap_or_default().trim();
+                code.strip_prefix('(')?
+                    .strip_suffix(')')
+                    .map(str::to_string)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::hack_cpu,
+        command::Command,
+        golden::{assert_golden_files, golden_dir, test_programs},
+        program::translate_file,
+    };
+
+    #[test]
+    fn test_programs_match_golden_files() {
+        let backend = &HackBackend::STANDARD;
+        let cases: Vec<(String, String)> = test_programs()
+            .iter()
+            .map(|path| {
+                let name = path.with_extension("asm");
+                let output = format!(
+                    "{}{}{}",
+                    backend.prologue(),
+                    translate_file(path, backend).unwrap(),
+                    backend.epilogue()
+                );
+                (
+                    name.file_name().unwrap().to_string_lossy().into_owned(),
+                    output,
+                )
+            })
+            .collect();
+        assert_golden_files(&golden_dir("hack"), &cases);
+    }
+
+    fn translate(backend: &HackBackend, program: &str) -> String {
+        let mut converter = backend.converter("Main");
+        let mut output = backend.prologue();
+        for line in program.lines() {
+            output.push_str(&converter.convert(Command::from(line).unwrap()).unwrap());
+            output.push('\n');
+        }
+        output
+    }
+
+    #[test]
+    fn test_custom_layout_runs() {
+        let layout = MemoryLayout {
+            sp: 10,
+            lcl: 11,
+            temp_base: 5,
+            temp_size: 4,
+            scratch: Some([13, 14]),
+            stack_base: 300,
+            ..MemoryLayout::STANDARD
+        };
+        layout.validate().unwrap();
+        let output = translate(
+            &HackBackend::new(layout, LabelScheme::DEFAULT),
+            "push constant 7\npop temp 3\npush constant 9\npop local 1\npush temp 3\npush local 1\nadd",
+        );
+        assert!(
+            !output.contains("@SP\n") && !output.contains("@tmp"),
+            "{}",
+            output
+        );
+
+        let program = hack_cpu::assemble(&output).unwrap();
+        let mut ram = vec![0; 32768];
+        ram[11] = 400;
+        hack_cpu::execute(&program, &mut ram).unwrap();
+        assert_eq!(ram[10], 301);
+        assert_eq!(ram[300], 16);
+        assert_eq!(ram[8], 7);
+        assert_eq!(ram[401], 9);
+    }
+
+    #[test]
+    fn test_temp_size_limits_indexes() {
+        let layout = MemoryLayout {
+            temp_size: 2,
+            ..MemoryLayout::STANDARD
+        };
+        let mut converter = HackBackend::new(layout, LabelScheme::DEFAULT).converter("Main");
+        assert!(
+            converter
+                .convert(Command::from("push temp 1").unwrap())
+                .is_ok()
+        );
+        let err = converter
+            .convert(Command::from("pop temp 2").unwrap())
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid index for temp. Expected 0 to 1 but got 2"
+        );
+    }
+
+    #[test]
+    fn test_label_scheme_names_labels_and_variables() {
+        let labels = LabelScheme::parse("{file}__{kind}__{n}").unwrap();
+        let backend = HackBackend::new(MemoryLayout::STANDARD, labels);
+        let output = translate(
+            &backend,
+            "push constant 1\npush constant 1\neq\npop local 0",
+        );
+        assert_eq!(
+            backend.defined_labels(&output),
+            ["Main__IsEqual__1", "Main__NotEqual__1", "Main__EqEnd__1"]
+        );
+        assert!(output.contains("@Main__tmp__1\n"), "{}", output);
+        assert!(!output.contains("Main.IsEqual"), "{}", output);
+        assert_ne!(backend.fingerprint(), HackBackend::STANDARD.fingerprint());
+    }
+}
@@ -0,0 +1,179 @@
+//! A small Hack assembler and CPU emulator, used by tests to execute the output of the Hack
+//! backend and compare the resulting RAM with the interpreter.
+
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow};
+
+use crate::interpreter::RAM_SIZE;
+
+const FIRST_VARIABLE: u16 = 16;
+const MAX_STEPS: usize = 1_000_000;
+
+enum Instruction {
+    Address(u16),
+    Compute {
+        comp: String,
+        dest: String,
+        jump: String,
+    },
+}
+
+/// An assembled Hack program, along with the addresses assigned to its variables.
+pub struct Program {
+    instructions: Vec<Instruction>,
+    pub symbols: HashMap<String, u16>,
+}
+
+pub fn assemble(source: &str) -> Result<Program> {
+    let lines: Vec<&str> = source
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+
+    let mut symbols: HashMap<String, u16> = HashMap::new();
+    for (name, address) in [("SP", 0), ("LCL", 1), ("ARG", 2), ("THIS", 3), ("THAT", 4)] {
+        symbols.insert(name.to_string(), address);
+    }
+    for register in 0..16 {
+        symbols.insert(format!("R{}", register), register);
+    }
+    symbols.insert("SCREEN".to_string(), 16384);
+    symbols.insert("KBD".to_string(), 24576);
+
+    // First pass: label declarations
+    let mut rom_address = 0;
+    for line in &lines {
+        if let Some(label) = line.strip_prefix('(').and_then(|l| l.strip_suffix(')')) {
+            if symbols.insert(label.to_string(), rom_address).is_some() {
+                return Err(anyhow!("Duplicate label: {}", label));
+            }
+        } else {
+            rom_address += 1;
+        }
+    }
+
+    // Second pass: instructions, allocating variables in order of first use
+    let mut next_variable = FIRST_VARIABLE;
+    let mut instructions = Vec::new();
+    for line in lines {
+        if line.starts_with('(') {
+            continue;
+        }
+
+        if let Some(value) = line.strip_prefix('@') {
+            let address = match value.parse::<u16>() {
+                Ok(address) => address,
+                Err(_) => *symbols.entry(value.to_string()).or_insert_with(|| {
+                    next_variable += 1;
+                    next_variable - 1
+                }),
+            };
+            instructions.push(Instruction::Address(address));
+            continue;
+        }
+
+        let (dest, rest) = line.split_once('=').unwrap_or(("", line));
+        let (comp, jump) = rest.split_once(';').unwrap_or((rest, ""));
+        instructions.push(Instruction::Compute {
+            comp: comp.replace(' ', ""),
+            dest: dest.trim().to_string(),
+            jump: jump.trim().to_string(),
+        });
+    }
+
+    Ok(Program {
+        instructions,
+        symbols,
+    })
+}
+
+/// Runs `program` until the program counter falls off the end of ROM.
+pub fn execute(program: &Program, ram: &mut [i16]) -> Result<()> {
+    assert_eq!(ram.len(), RAM_SIZE);
+    let (mut a, mut d, mut pc) = (0i16, 0i16, 0usize);
+
+    for _ in 0..MAX_STEPS {
+        let Some(instruction) = program.instructions.get(pc) else {
+            return Ok(());
+        };
+
+        match instruction {
+            Instruction::Address(address) => {
+                a = *address as i16;
+                pc += 1;
+            }
+            Instruction::Compute { comp, dest, jump } => {
+                let m = ram[a as u16 as usize % RAM_SIZE];
+                let value = alu(comp, a, d, m)?;
+
+                // M is written using the A register from before this instruction
+                if dest.contains('M') {
+                    ram[a as u16 as usize % RAM_SIZE] = value;
+                }
+                if dest.contains('D') {
+                    d = value;
+                }
+                let target = a;
+                if dest.contains('A') {
+                    a = value;
+                }
+
+                let taken = match jump.as_str() {
+                    "" => false,
+                    "JGT" => value > 0,
+                    "JEQ" => value == 0,
+                    "JGE" => value >= 0,
+                    "JLT" => value < 0,
+                    "JNE" => value != 0,
+                    "JLE" => value <= 0,
+                    "JMP" => true,
+                    _ => return Err(anyhow!("Unknown jump: {}", jump)),
+                };
+                pc = if taken {
+                    target as u16 as usize
+                } else {
+                    pc + 1
+                };
+            }
+        }
+    }
+
+    Err(anyhow!("Program did not finish within {} steps", MAX_STEPS))
+}
+
+fn alu(comp: &str, a: i16, d: i16, m: i16) -> Result<i16> {
+    let value = match comp {
+        "0" => 0,
+        "1" => 1,
+        "-1" => -1,
+        "D" => d,
+        "A" => a,
+        "M" => m,
+        "!D" => !d,
+        "!A" => !a,
+        "!M" => !m,
+        "-D" => d.wrapping_neg(),
+        "-A" => a.wrapping_neg(),
+        "-M" => m.wrapping_neg(),
+        "D+1" => d.wrapping_add(1),
+        "A+1" => a.wrapping_add(1),
+        "M+1" => m.wrapping_add(1),
+        "D-1" => d.wrapping_sub(1),
+        "A-1" => a.wrapping_sub(1),
+        "M-1" => m.wrapping_sub(1),
+        "D+A" | "A+D" => d.wrapping_add(a),
+        "D+M" | "M+D" => d.wrapping_add(m),
+        "D-A" => d.wrapping_sub(a),
+        "D-M" => d.wrapping_sub(m),
+        "A-D" => a.wrapping_sub(d),
+        "M-D" => m.wrapping_sub(d),
+        "D&A" | "A&D" => d & a,
+        "D&M" | "M&D" => d & m,
+        "D|A" | "A|D" => d | a,
+        "D|M" | "M|D" => d | m,
+        _ => return Err(anyhow!("Unknown computation: {}", comp)),
+    };
+    Ok(value)
+}
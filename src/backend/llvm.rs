@@ -0,0 +1,518 @@
+use anyhow::{Result, anyhow};
+
+use crate::{
+    backend::{Backend, SegmentAddress, segment_address},
+    command::{Command, Segment},
+    converter::Converter,
+    memory_layout::MemoryLayout,
+};
+
+/// LLVM IR in text form, for running programs through LLVM's optimizer. The Hack RAM is a
+/// global `i16` array and every VM command calls a small internal helper, which the optimizer
+/// is free to inline. Each `.vm` file becomes an LLVM function named after the file, and
+/// `main` runs them in order with the same arguments and RAM dump as the C backend.
+pub struct LlvmBackend;
+
+impl Backend for LlvmBackend {
+    fn name(&self) -> &'static str {
+        "llvm"
+    }
+
+    fn description(&self) -> &'static str {
+        "LLVM IR in text form"
+    }
+
+    fn extension(&self) -> &'static str {
+        "ll"
+    }
+
+    fn converter(&self, file_name: &str) -> Box<dyn Converter> {
+        Box::new(LlvmConverter {
+            file_name: file_name.to_string(),
+            started: false,
+            next_value: 0,
+        })
+    }
+
+    fn prologue(&self) -> String {
+        let mut prologue = String::from("; Generated by vm_translator\n\n");
+        for (name, text) in STRINGS {
+            prologue.push_str(&string_constant(name, text));
+        }
+        prologue.push_str(RUNTIME);
+
+        for (name, operation) in BINARY_OPERATIONS {
+            prologue.push_str(&format!(
+                "\ndefine internal void @vm_{}() {{\n\
+                \x20 %y = call i16 @vm_pop()\n\
+                \x20 %x = call i16 @vm_pop()\n\
+                {}\
+                \x20 call void @vm_push(i16 %result)\n\
+                \x20 ret void\n}}\n",
+                name, operation
+            ));
+        }
+        for (name, operation) in UNARY_OPERATIONS {
+            prologue.push_str(&format!(
+                "\ndefine internal void @vm_{}() {{\n\
+                \x20 %x = call i16 @vm_pop()\n\
+                \x20 %result = {}\n\
+                \x20 call void @vm_push(i16 %result)\n\
+                \x20 ret void\n}}\n",
+                name, operation
+            ));
+        }
+
+        prologue
+            .push_str("\n; Runs the files in order\ndefine internal void @vm_files() {\nentry:\n");
+        prologue
+    }
+
+    fn epilogue(&self) -> String {
+        "  ret void\n}\n".to_string()
+    }
+}
+
+const STRINGS: [(&str, &str); 8] = [
+    ("vm_stack_overflow_message", "Stack overflow"),
+    ("vm_stack_underflow_message", "Stack underflow"),
+    ("vm_segment_overflow_message", "Segment access outside RAM"),
+    ("vm_too_many_statics_message", "Too many static variables"),
+    ("vm_error_format", "Error: %s\n"),
+    (
+        "vm_assignment_format",
+        "Invalid assignment %s, expected ADDRESS=VALUE\n",
+    ),
+    ("vm_ram_format", "ram %d %d\n"),
+    ("vm_static_format", "static %s.%d %d\n"),
+];
+
+// The right operand is on top of the stack, so it is popped first
+const BINARY_OPERATIONS: [(&str, &str); 7] = [
+    ("add", "  %result = add i16 %x, %y\n"),
+    ("sub", "  %result = sub i16 %x, %y\n"),
+    ("and", "  %result = and i16 %x, %y\n"),
+    ("or", "  %result = or i16 %x, %y\n"),
+    (
+        "eq",
+        "  %true = icmp eq i16 %x, %y\n  %result = sext i1 %true to i16\n",
+    ),
+    (
+        "gt",
+        "  %true = icmp sgt i16 %x, %y\n  %result = sext i1 %true to i16\n",
+    ),
+    (
+        "lt",
+        "  %true = icmp slt i16 %x, %y\n  %result = sext i1 %true to i16\n",
+    ),
+];
+
+const UNARY_OPERATIONS: [(&str, &str); 2] = [("neg", "sub i16 0, %x"), ("not", "xor i16 %x, -1")];
+
+// Static variables get the next free address the first time they are used, like in the
+// interpreter. They are keyed by the name constant of their file and their index.
+const RUNTIME: &str = r#"
+@vm_ram = global [32768 x i16] zeroinitializer
+@vm_static_files = internal global [240 x ptr] zeroinitializer
+@vm_static_indexes = internal global [240 x i32] zeroinitializer
+@vm_static_count = internal global i32 0
+@vm_chained = internal global i1 false
+@stderr = external global ptr
+
+declare i32 @printf(ptr, ...)
+declare i32 @fprintf(ptr, ptr, ...)
+declare i64 @strtol(ptr, ptr, i32)
+declare void @exit(i32)
+
+define internal void @vm_fail(ptr %message) {
+  %stderr = load ptr, ptr @stderr
+  call i32 (ptr, ptr, ...) @fprintf(ptr %stderr, ptr @vm_error_format, ptr %message)
+  call void @exit(i32 1)
+  unreachable
+}
+
+define internal i16 @vm_peek(i32 %address) {
+  %cell = getelementptr [32768 x i16], ptr @vm_ram, i32 0, i32 %address
+  %value = load i16, ptr %cell
+  ret i16 %value
+}
+
+define internal void @vm_poke(i32 %address, i16 %value) {
+  %cell = getelementptr [32768 x i16], ptr @vm_ram, i32 0, i32 %address
+  store i16 %value, ptr %cell
+  ret void
+}
+
+define internal void @vm_push(i16 %value) {
+entry:
+  %sp16 = call i16 @vm_peek(i32 0)
+  %sp = zext i16 %sp16 to i32
+  %full = icmp uge i32 %sp, 32768
+  br i1 %full, label %overflow, label %store
+overflow:
+  call void @vm_fail(ptr @vm_stack_overflow_message)
+  unreachable
+store:
+  call void @vm_poke(i32 %sp, i16 %value)
+  %next = add i16 %sp16, 1
+  call void @vm_poke(i32 0, i16 %next)
+  ret void
+}
+
+define internal i16 @vm_pop() {
+entry:
+  %sp16 = call i16 @vm_peek(i32 0)
+  %sp = zext i16 %sp16 to i32
+  %empty = icmp ule i32 %sp, 256
+  br i1 %empty, label %underflow, label %load
+underflow:
+  call void @vm_fail(ptr @vm_stack_underflow_message)
+  unreachable
+load:
+  %top = sub i32 %sp, 1
+  %top16 = trunc i32 %top to i16
+  call void @vm_poke(i32 0, i16 %top16)
+  %value = call i16 @vm_peek(i32 %top)
+  ret i16 %value
+}
+
+; Address of entry %index of the segment whose base is stored at %pointer
+define internal i32 @vm_segment(i32 %pointer, i32 %index) {
+entry:
+  %base16 = call i16 @vm_peek(i32 %pointer)
+  %base = zext i16 %base16 to i32
+  %address = add i32 %base, %index
+  %outside = icmp uge i32 %address, 32768
+  br i1 %outside, label %fail, label %done
+fail:
+  call void @vm_fail(ptr @vm_segment_overflow_message)
+  unreachable
+done:
+  ret i32 %address
+}
+
+; %name is the constant holding the name of the file the static belongs to
+define internal i32 @vm_static(ptr %name, i32 %index) {
+entry:
+  %count = load i32, ptr @vm_static_count
+  br label %search
+search:
+  %i = phi i32 [ 0, %entry ], [ %next, %continue ]
+  %searched = icmp uge i32 %i, %count
+  br i1 %searched, label %allocate, label %compare
+compare:
+  %name_slot = getelementptr [240 x ptr], ptr @vm_static_files, i32 0, i32 %i
+  %existing_name = load ptr, ptr %name_slot
+  %index_slot = getelementptr [240 x i32], ptr @vm_static_indexes, i32 0, i32 %i
+  %existing_index = load i32, ptr %index_slot
+  %same_name = icmp eq ptr %existing_name, %name
+  %same_index = icmp eq i32 %existing_index, %index
+  %found = and i1 %same_name, %same_index
+  br i1 %found, label %done, label %continue
+continue:
+  %next = add i32 %i, 1
+  br label %search
+allocate:
+  %full = icmp uge i32 %count, 240
+  br i1 %full, label %fail, label %store
+fail:
+  call void @vm_fail(ptr @vm_too_many_statics_message)
+  unreachable
+store:
+  %new_name = getelementptr [240 x ptr], ptr @vm_static_files, i32 0, i32 %count
+  store ptr %name, ptr %new_name
+  %new_index = getelementptr [240 x i32], ptr @vm_static_indexes, i32 0, i32 %count
+  store i32 %index, ptr %new_index
+  %new_count = add i32 %count, 1
+  store i32 %new_count, ptr @vm_static_count
+  br label %done
+done:
+  %position = phi i32 [ %i, %compare ], [ %count, %store ]
+  %address = add i32 %position, 16
+  ret i32 %address
+}
+
+define internal void @vm_dump() {
+entry:
+  br label %loop
+loop:
+  %i = phi i32 [ 0, %entry ], [ %next, %continue ]
+  %value = call i16 @vm_peek(i32 %i)
+  %nonzero = icmp ne i16 %value, 0
+  br i1 %nonzero, label %print, label %continue
+print:
+  %wide = sext i16 %value to i32
+  call i32 (ptr, ...) @printf(ptr @vm_ram_format, i32 %i, i32 %wide)
+  br label %continue
+continue:
+  %next = add i32 %i, 1
+  %finished = icmp eq i32 %next, 32768
+  br i1 %finished, label %statics, label %loop
+statics:
+  %count = load i32, ptr @vm_static_count
+  br label %static_loop
+static_loop:
+  %s = phi i32 [ 0, %statics ], [ %next_static, %print_static ]
+  %more = icmp ult i32 %s, %count
+  br i1 %more, label %print_static, label %exit
+print_static:
+  %name_slot = getelementptr [240 x ptr], ptr @vm_static_files, i32 0, i32 %s
+  %name = load ptr, ptr %name_slot
+  %index_slot = getelementptr [240 x i32], ptr @vm_static_indexes, i32 0, i32 %s
+  %index = load i32, ptr %index_slot
+  %address = add i32 %s, 16
+  %static_value = call i16 @vm_peek(i32 %address)
+  %static_wide = sext i16 %static_value to i32
+  call i32 (ptr, ...) @printf(ptr @vm_static_format, ptr %name, i32 %index, i32 %static_wide)
+  %next_static = add i32 %s, 1
+  br label %static_loop
+exit:
+  ret void
+}
+
+; Each argument is ADDRESS=VALUE
+define i32 @main(i32 %argc, ptr %argv) {
+entry:
+  %end = alloca ptr
+  call void @vm_poke(i32 0, i16 256)
+  br label %check
+check:
+  %i = phi i32 [ 1, %entry ], [ %next, %assign ]
+  %more = icmp slt i32 %i, %argc
+  br i1 %more, label %parse, label %run
+parse:
+  %slot = getelementptr ptr, ptr %argv, i32 %i
+  %argument = load ptr, ptr %slot
+  %address = call i64 @strtol(ptr %argument, ptr %end, i32 10)
+  %rest = load ptr, ptr %end
+  %separator = load i8, ptr %rest
+  %is_assignment = icmp eq i8 %separator, 61
+  %in_range = icmp ult i64 %address, 32768
+  %valid = and i1 %is_assignment, %in_range
+  br i1 %valid, label %assign, label %invalid
+assign:
+  %value_text = getelementptr i8, ptr %rest, i32 1
+  %value = call i64 @strtol(ptr %value_text, ptr null, i32 10)
+  %address32 = trunc i64 %address to i32
+  %value16 = trunc i64 %value to i16
+  call void @vm_poke(i32 %address32, i16 %value16)
+  %next = add i32 %i, 1
+  br label %check
+invalid:
+  %stderr = load ptr, ptr @stderr
+  call i32 (ptr, ptr, ...) @fprintf(ptr %stderr, ptr @vm_assignment_format, ptr %argument)
+  ret i32 2
+run:
+  store i1 true, ptr @vm_chained
+  call void @vm_files()
+  store i1 false, ptr @vm_chained
+  call void @vm_dump()
+  ret i32 0
+}
+"#;
+
+struct LlvmConverter {
+    file_name: String,
+    // Whether the function for this file has been opened yet
+    started: bool,
+    // Numbers the SSA values of this file's function
+    next_value: usize,
+}
+
+impl LlvmConverter {
+    /// Ends the previous file's function (or `@vm_files`, for the first file) with a call to
+    /// this file's function when running the whole program, and opens this file's function.
+    fn header(&self) -> String {
+        let function = global_name("vm_file.", &self.file_name);
+        format!(
+            "  %chained = load i1, ptr @vm_chained\n\
+            \x20 br i1 %chained, label %next, label %done\n\
+            next:\n\
+            \x20 call void {function}()\n\
+            \x20 br label %done\n\
+            done:\n\
+            \x20 ret void\n}}\n\n\
+            {name}\n\
+            define void {function}() {{\n\
+            entry:\n",
+            function = function,
+            name = string_constant(
+                &global_name("vm_name.", &self.file_name)[1..],
+                &self.file_name
+            )
+        )
+    }
+
+    fn value(&mut self) -> String {
+        self.next_value += 1;
+        format!("%v{}", self.next_value)
+    }
+
+    /// Instructions computing the address of a segment entry, and the operand holding it.
+    fn address(&mut self, segment: Segment, index: u16) -> Result<(String, String)> {
+        let (pointer, index) = match segment_address(&MemoryLayout::STANDARD, segment, index)? {
+            SegmentAddress::Based { pointer, index } => (pointer, index),
+            SegmentAddress::Fixed(address) => return Ok((String::new(), address.to_string())),
+            SegmentAddress::Static(index) => {
+                let address = self.value();
+                let code = format!(
+                    "  {} = call i32 @vm_static(ptr {}, i32 {})\n",
+                    address,
+                    global_name("vm_name.", &self.file_name),
+                    index
+                );
+                return Ok((code, address));
+            }
+        };
+
+        let address = self.value();
+        let code = format!(
+            "  {} = call i32 @vm_segment(i32 {}, i32 {})\n",
+            address, pointer, index
+        );
+        Ok((code, address))
+    }
+}
+
+impl Converter for LlvmConverter {
+    fn convert(&mut self, command: Command) -> Result<String> {
+        let code = match command {
+            Command::Push {
+                segment: Segment::Constant,
+                index,
+            } => format!("  call void @vm_push(i16 {})\n", index as i16),
+            Command::Pop {
+                segment: Segment::Constant,
+                ..
+            } => return Err(anyhow!("Cannot pop constant")),
+            Command::Push { segment, index } => {
+                let (mut code, address) = self.address(segment, index)?;
+                let value = self.value();
+                code.push_str(&format!(
+                    "  {value} = call i16 @vm_peek(i32 {address})\n\
+                    \x20 call void @vm_push(i16 {value})\n",
+                    value = value,
+                    address = address
+                ));
+                code
+            }
+            Command::Pop { segment, index } => {
+                let (mut code, address) = self.address(segment, index)?;
+                let value = self.value();
+                code.push_str(&format!(
+                    "  {value} = call i16 @vm_pop()\n\
+                    \x20 call void @vm_poke(i32 {address}, i16 {value})\n",
+                    value = value,
+                    address = address
+                ));
+                code
+            }
+            Command::Add => "  call void @vm_add()\n".to_string(),
+            Command::Sub => "  call void @vm_sub()\n".to_string(),
+            Command::And => "  call void @vm_and()\n".to_string(),
+            Command::Or => "  call void @vm_or()\n".to_string(),
+            Command::Eq => "  call void @vm_eq()\n".to_string(),
+            Command::Gt => "  call void @vm_gt()\n".to_string(),
+            Command::Lt => "  call void @vm_lt()\n".to_string(),
+            Command::Neg => "  call void @vm_neg()\n".to_string(),
+            Command::Not => "  call void @vm_not()\n".to_string(),
+        };
+
+        let header = if self.started {
+            String::new()
+        } else {
+            self.started = true;
+            self.header()
+        };
+        Ok(format!("{}  ; {}\n{}", header, command, code.trim_end()))
+    }
+
+    // A file without commands still gets its function, which does nothing
+    fn finish(&mut self) -> String {
+        if self.started {
+            return String::new();
+        }
+        self.started = true;
+        self.header().trim_end().to_string()
+    }
+}
+
+/// A quoted global identifier, which may contain any character once escaped.
+fn global_name(prefix: &str, file_name: &str) -> String {
+    format!("@\"{}{}\"", prefix, escape(file_name))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'"' | b'\\' => format!("\\{:02X}", byte),
+            b' '..=b'~' => (byte as char).to_string(),
+            _ => format!("\\{:02X}", byte),
+        })
+        .collect()
+}
+
+/// A private NUL-terminated string constant.
+fn string_constant(name: &str, text: &str) -> String {
+    format!(
+        "@{} = private constant [{} x i8] c\"{}\\00\"\n",
+        name,
+        text.len() + 1,
+        escape(text)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands_become_calls() {
+        let mut converter = LlvmBackend.converter("Main");
+        let first = converter
+            .convert(Command::from("push local 2").unwrap())
+            .unwrap();
+        assert!(first.starts_with("  %chained = load i1, ptr @vm_chained\n"));
+        assert!(first.contains("define void @\"vm_file.Main\"() {\n"));
+        assert!(first.ends_with(
+            "  ; push local 2\n\
+            \x20 %v1 = call i32 @vm_segment(i32 1, i32 2)\n\
+            \x20 %v2 = call i16 @vm_peek(i32 %v1)\n\
+            \x20 call void @vm_push(i16 %v2)"
+        ));
+
+        // Only the first command opens the function, and values keep their numbering
+        let second = converter
+            .convert(Command::from("pop temp 1").unwrap())
+            .unwrap();
+        assert_eq!(
+            second,
+            "  ; pop temp 1\n\
+            \x20 %v3 = call i16 @vm_pop()\n\
+            \x20 call void @vm_poke(i32 6, i16 %v3)"
+        );
+    }
+
+    #[test]
+    fn test_files_without_commands_get_a_function() {
+        let mut empty = LlvmBackend.converter("Empty");
+        let end = empty.finish();
+        assert!(end.contains("  call void @\"vm_file.Empty\"()\n"));
+        assert!(end.ends_with("define void @\"vm_file.Empty\"() {\nentry:"));
+
+        let mut main = LlvmBackend.converter("Main");
+        main.convert(Command::Add).unwrap();
+        assert_eq!(main.finish(), "");
+    }
+
+    #[test]
+    fn test_names_and_strings_are_escaped() {
+        assert_eq!(global_name("vm_file.", "Main"), "@\"vm_file.Main\"");
+        assert_eq!(global_name("vm_file.", "a\"b"), "@\"vm_file.a\\22b\"");
+        assert_eq!(
+            string_constant("s", "Hi\n"),
+            "@s = private constant [4 x i8] c\"Hi\\0A\\00\"\n"
+        );
+    }
+}
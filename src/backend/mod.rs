@@ -0,0 +1,220 @@
+use anyhow::{Result, anyhow};
+
+use crate::{command::Segment, converter::Converter, memory_layout::MemoryLayout};
+
+pub mod c;
+pub mod hack;
+pub mod llvm;
+pub mod rv32i;
+pub mod wasm;
+pub mod x86_64;
+
+#[cfg(test)]
+mod conformance;
+#[cfg(test)]
+pub(crate) mod hack_cpu;
+#[cfg(test)]
+mod rv32i_cpu;
+
+/// A code generator that can be selected with `--target`. A backend hands out a fresh
+/// [`Converter`] for every source file, and can wrap the combined output of all files in a
+/// prologue and epilogue.
+pub trait Backend: Sync {
+    /// Name used to select the backend on the command line
+    fn name(&self) -> &'static str;
+
+    /// One line summary shown in `--help`
+    fn description(&self) -> &'static str;
+
+    /// Extension of the generated output file, without the leading dot
+    fn extension(&self) -> &'static str;
+
+    /// A converter for one source file. `file_name` scopes static variables and labels.
+    fn converter(&self, file_name: &str) -> Box<dyn Converter>;
+
+    /// Emitted once before the output of the first file
+    fn prologue(&self) -> String {
+        String::new()
+    }
+
+    /// Emitted once after the output of the last file
+    fn epilogue(&self) -> String {
+        String::new()
+    }
+
+    /// Settings other than the VM code that change the output, so cached output made with
+    /// different settings is not reused. Empty for the default settings.
+    fn fingerprint(&self) -> String {
+        String::new()
+    }
+
+    /// The labels a fragment of this backend's output defines, so the fragments of a
+    /// program can be checked for clashes before they are concatenated. Backends whose
+    /// labels are local to each file's code return none.
+    fn defined_labels(&self, _fragment: &str) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+static HACK: hack::HackBackend = hack::HackBackend::STANDARD;
+
+static BACKENDS: &[&dyn Backend] = &[
+    &HACK,
+    &c::CBackend,
+    &x86_64::X86_64Backend,
+    &wasm::WasmBackend,
+    &llvm::LlvmBackend,
+    &rv32i::Rv32iBackend,
+];
+
+pub fn backends() -> &'static [&'static dyn Backend] {
+    BACKENDS
+}
+
+pub fn default_backend() -> &'static dyn Backend {
+    &HACK
+}
+
+pub fn find_backend(name: &str) -> Result<&'static dyn Backend> {
+    BACKENDS
+        .iter()
+        .copied()
+        .find(|backend| backend.name() == name)
+        .ok_or_else(|| {
+            let names: Vec<&str> = BACKENDS.iter().map(|backend| backend.name()).collect();
+            anyhow!(
+                "Unknown target: {}. Expected one of {}",
+                name,
+                names.join(", ")
+            )
+        })
+}
+
+/// Where the entry of a segment is in RAM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SegmentAddress {
+    /// `index` cells past the address held in the pointer register at `pointer`, for
+    /// `local`, `argument`, `this` and `that`
+    Based { pointer: u16, index: u16 },
+    /// A fixed address, for `pointer` and `temp`
+    Fixed(u16),
+    /// A static variable, which each backend allocates its own way
+    Static(u16),
+}
+
+/// Resolves `segment index` to an address in `layout`, checking the index of the fixed
+/// size segments. `constant` has no address.
+pub(crate) fn segment_address(
+    layout: &MemoryLayout,
+    segment: Segment,
+    index: u16,
+) -> Result<SegmentAddress> {
+    let based = |pointer| SegmentAddress::Based { pointer, index };
+    let address = match segment {
+        Segment::Local => based(layout.lcl),
+        Segment::Argument => based(layout.arg),
+        Segment::This => based(layout.this),
+        Segment::That => based(layout.that),
+        Segment::Pointer => match index {
+            0 => SegmentAddress::Fixed(layout.this),
+            1 => SegmentAddress::Fixed(layout.that),
+            _ => {
+                return Err(anyhow!(
+                    "Invalid index for pointer. Expected ( 0 , 1 ) but got {}",
+                    index
+                ));
+            }
+        },
+        Segment::Temp => {
+            if index >= layout.temp_size {
+                return Err(anyhow!(
+                    "Invalid index for temp. Expected 0 to {} but got {}",
+                    layout.temp_size.saturating_sub(1),
+                    index
+                ));
+            }
+            SegmentAddress::Fixed(layout.temp_base + index)
+        }
+        Segment::Static => SegmentAddress::Static(index),
+        Segment::Constant => return Err(anyhow!("constant has no address")),
+    };
+    Ok(address)
+}
+
+/// Quotes `value` as a C string literal, a syntax the GNU assembler also accepts.
+pub(crate) fn c_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            ' '..='~' => quoted.push(c),
+            _ => {
+                for byte in c.to_string().bytes() {
+                    quoted.push_str(&format!("\\{:03o}", byte));
+                }
+            }
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Turns a file name into something usable in a symbol. Every byte other than an ASCII letter
+/// or digit is hex-escaped, so different file names never produce the same symbol.
+pub(crate) fn mangle(file_name: &str) -> String {
+    file_name
+        .bytes()
+        .map(|byte| {
+            if byte.is_ascii_alphanumeric() {
+                (byte as char).to_string()
+            } else {
+                format!("_{:02x}", byte)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_addresses_follow_the_layout() {
+        let layout = MemoryLayout {
+            lcl: 11,
+            that: 12,
+            temp_base: 6,
+            temp_size: 2,
+            ..MemoryLayout::STANDARD
+        };
+        let resolve = |segment, index| segment_address(&layout, segment, index);
+        assert_eq!(
+            resolve(Segment::Local, 4).unwrap(),
+            SegmentAddress::Based {
+                pointer: 11,
+                index: 4
+            }
+        );
+        assert_eq!(
+            resolve(Segment::Pointer, 1).unwrap(),
+            SegmentAddress::Fixed(12)
+        );
+        assert_eq!(resolve(Segment::Temp, 1).unwrap(), SegmentAddress::Fixed(7));
+        assert_eq!(
+            resolve(Segment::Static, 9).unwrap(),
+            SegmentAddress::Static(9)
+        );
+
+        for (segment, index, expected) in [
+            (Segment::Pointer, 2, "Expected ( 0 , 1 ) but got 2"),
+            (Segment::Temp, 2, "Expected 0 to 1 but got 2"),
+            (Segment::Constant, 0, "constant has no address"),
+        ] {
+            let err = resolve(segment, index).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::HashSet;
+
+use anyhow::{Result, anyhow};
+
+use crate::{
+    backend::{Backend, SegmentAddress, mangle, segment_address},
+    command::{Command, Segment},
+    converter::Converter,
+    memory_layout::MemoryLayout,
+};
+
+/// RV32I assembly for the GNU assembler, for showing VM programs lowered to a real RISC ISA.
+/// The Hack RAM is the global halfword array `vm_ram`, with `SP` starting at 256, and the
+/// program is the function `vm_run`. Like the Hack output, every command is expanded in full
+/// and static variables are assembler symbols.
+pub struct Rv32iBackend;
+
+impl Backend for Rv32iBackend {
+    fn name(&self) -> &'static str {
+        "rv32i"
+    }
+
+    fn description(&self) -> &'static str {
+        "RV32I assembly for the GNU assembler"
+    }
+
+    fn extension(&self) -> &'static str {
+        "s"
+    }
+
+    fn converter(&self, file_name: &str) -> Box<dyn Converter> {
+        Box::new(Rv32iConverter {
+            file_name: file_name.to_string(),
+            statics: HashSet::new(),
+            definitions: String::new(),
+        })
+    }
+
+    fn prologue(&self) -> String {
+        PROLOGUE.to_string()
+    }
+
+    fn epilogue(&self) -> String {
+        EPILOGUE.to_string()
+    }
+}
+
+// s0 holds the address of vm_ram throughout, and is restored before returning
+const PROLOGUE: &str = "# Generated by vm_translator
+    .data
+    .globl vm_ram
+    .balign 4
+vm_ram:
+    .half 256
+    .zero 65534
+
+    .text
+    .globl vm_run
+vm_run:
+    addi sp, sp, -16
+    sw s0, 12(sp)
+    la s0, vm_ram
+
+";
+
+const EPILOGUE: &str = "
+    lw s0, 12(sp)
+    addi sp, sp, 16
+    ret
+";
+
+// Push t0, using t1 and t2
+const PUSH_T0: &str = "    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+";
+
+// Pop into t0, using t1 and t2
+const POP_T0: &str = "    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+";
+
+// Largest index that fits in an I-type immediate
+const MAX_IMMEDIATE: u16 = 2047;
+
+struct Rv32iConverter {
+    // Scopes static variables, so files can be concatenated without their symbols colliding
+    file_name: String,
+    // Statics whose symbols have already been defined in this file's output
+    statics: HashSet<u16>,
+    // Symbol definitions needed by the command being converted
+    definitions: String,
+}
+
+enum Location {
+    // Address of the cell relative to s0
+    Fixed(u16),
+    // Address of the cell in t3
+    Computed(String),
+}
+
+impl Rv32iConverter {
+    fn location(&mut self, segment: Segment, index: u16) -> Result<Location> {
+        let (pointer, index) = match segment_address(&MemoryLayout::STANDARD, segment, index)? {
+            SegmentAddress::Based { pointer, index } => (pointer, index),
+            SegmentAddress::Fixed(address) => return Ok(Location::Fixed(address)),
+            SegmentAddress::Static(index) => {
+                let symbol = format!("vm_static.{}.{}", mangle(&self.file_name), index);
+                if self.statics.insert(index) {
+                    self.definitions = format!(
+                        "    .pushsection .bss\n    .balign 2\n{}:\n    .zero 2\n    .popsection\n",
+                        symbol
+                    );
+                }
+                return Ok(Location::Computed(format!("    la t3, {}\n", symbol)));
+            }
+        };
+
+        let mut code = format!("    lhu t3, {}(s0)\n", pointer * 2);
+        if index <= MAX_IMMEDIATE {
+            code.push_str(&format!("    addi t3, t3, {}\n", index));
+        } else {
+            code.push_str(&format!("    li t4, {}\n    add t3, t3, t4\n", index));
+        }
+        code.push_str("    slli t3, t3, 1\n    add t3, s0, t3\n");
+        Ok(Location::Computed(code))
+    }
+}
+
+impl Converter for Rv32iConverter {
+    fn convert(&mut self, command: Command) -> Result<String> {
+        let code = match command {
+            Command::Push {
+                segment: Segment::Constant,
+                index,
+            } => format!("    li t0, {}\n{}", index as i16, PUSH_T0),
+            Command::Pop {
+                segment: Segment::Constant,
+                ..
+            } => return Err(anyhow!("Cannot pop constant")),
+            Command::Push { segment, index } => match self.location(segment, index)? {
+                Location::Fixed(address) => {
+                    format!("    lh t0, {}(s0)\n{}", address * 2, PUSH_T0)
+                }
+                Location::Computed(code) => format!("{}    lh t0, 0(t3)\n{}", code, PUSH_T0),
+            },
+            Command::Pop { segment, index } => match self.location(segment, index)? {
+                Location::Fixed(address) => {
+                    format!("{}    sh t0, {}(s0)\n", POP_T0, address * 2)
+                }
+                Location::Computed(code) => format!("{}{}    sh t0, 0(t3)\n", code, POP_T0),
+            },
+            Command::Add => binary("    add t0, t0, t4\n"),
+            Command::Sub => binary("    sub t0, t0, t4\n"),
+            Command::And => binary("    and t0, t0, t4\n"),
+            Command::Or => binary("    or t0, t0, t4\n"),
+            Command::Eq => binary("    sub t0, t0, t4\n    seqz t0, t0\n    neg t0, t0\n"),
+            Command::Gt => binary("    slt t0, t4, t0\n    neg t0, t0\n"),
+            Command::Lt => binary("    slt t0, t0, t4\n    neg t0, t0\n"),
+            Command::Neg => format!("{}    neg t0, t0\n{}", POP_T0, PUSH_T0),
+            Command::Not => format!("{}    not t0, t0\n{}", POP_T0, PUSH_T0),
+        };
+
+        // Static definitions switch sections, so they go before the comment
+        Ok(format!(
+            "{}    # {}\n{}",
+            std::mem::take(&mut self.definitions),
+            command,
+            code
+        ))
+    }
+}
+
+// The right operand is on top of the stack, so it ends up in t4 and the left one in t0.
+// Values are sign-extended when loaded, so signed comparisons work on the full registers.
+fn binary(operation: &str) -> String {
+    format!(
+        "{}    mv t4, t0\n{}{}{}",
+        POP_T0, POP_T0, operation, PUSH_T0
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        golden::{assert_golden_files, golden_dir, test_programs},
+        program::translate_file,
+    };
+
+    #[test]
+    fn test_programs_match_golden_files() {
+        let cases: Vec<(String, String)> = test_programs()
+            .iter()
+            .map(|path| {
+                let name = path.with_extension("s");
+                let output = format!(
+                    "{}{}{}",
+                    Rv32iBackend.prologue(),
+                    translate_file(path, &Rv32iBackend).unwrap(),
+                    Rv32iBackend.epilogue()
+                );
+                (
+                    name.file_name().unwrap().to_string_lossy().into_owned(),
+                    output,
+                )
+            })
+            .collect();
+        assert_golden_files(&golden_dir("rv32i"), &cases);
+    }
+
+    #[test]
+    fn test_large_segment_indexes_use_a_register() {
+        let mut converter = Rv32iBackend.converter("Main");
+        let small = converter
+            .convert(Command::from("push local 2047").unwrap())
+            .unwrap();
+        assert!(small.contains("    addi t3, t3, 2047\n"));
+
+        let large = converter
+            .convert(Command::from("push local 2048").unwrap())
+            .unwrap();
+        assert!(large.contains("    li t4, 2048\n    add t3, t3, t4\n"));
+    }
+}
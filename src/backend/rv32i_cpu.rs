@@ -0,0 +1,229 @@
+//! A small interpreter for the subset of RV32I assembly the RV32I backend emits, used by
+//! tests to run its output and compare the resulting RAM with the interpreter. It works on
+//! the assembly text directly rather than on encoded instructions.
+
+use std::collections::HashMap;
+
+use anyhow::{Context, Result, anyhow};
+
+const MEMORY_SIZE: usize = 1 << 20;
+// Keeps address 0 unused, so a missing symbol cannot alias real data
+const DATA_BASE: u32 = 0x1000;
+const RETURN_SENTINEL: u32 = u32::MAX;
+const MAX_STEPS: usize = 10_000_000;
+
+struct Instruction {
+    mnemonic: String,
+    operands: Vec<String>,
+}
+
+/// An assembled program: instructions, the labels in its code and the addresses of its data.
+pub struct Program {
+    instructions: Vec<Instruction>,
+    labels: HashMap<String, usize>,
+    pub symbols: HashMap<String, u32>,
+    memory: Vec<u8>,
+}
+
+pub fn assemble(source: &str) -> Result<Program> {
+    let mut program = Program {
+        instructions: Vec::new(),
+        labels: HashMap::new(),
+        symbols: HashMap::new(),
+        memory: vec![0; MEMORY_SIZE],
+    };
+    let mut in_text = false;
+    let mut sections: Vec<bool> = Vec::new();
+    let mut cursor = DATA_BASE;
+
+    for (number, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let context = || format!("line {}: {}", number + 1, line);
+
+        if let Some(label) = line.strip_suffix(':') {
+            let duplicate = if in_text {
+                program
+                    .labels
+                    .insert(label.to_string(), program.instructions.len())
+            } else {
+                program.symbols.insert(label.to_string(), cursor).map(|_| 0)
+            };
+            if duplicate.is_some() {
+                return Err(anyhow!("Duplicate label: {}", label));
+            }
+            continue;
+        }
+
+        let (mnemonic, rest) = line.split_once(' ').unwrap_or((line, ""));
+        let operands: Vec<String> = rest
+            .split(',')
+            .map(|operand| operand.trim().to_string())
+            .filter(|operand| !operand.is_empty())
+            .collect();
+
+        match mnemonic {
+            ".text" => in_text = true,
+            ".data" | ".bss" => in_text = false,
+            ".pushsection" => {
+                sections.push(in_text);
+                in_text = operands.first().is_some_and(|section| section == ".text");
+            }
+            ".popsection" => {
+                in_text = sections
+                    .pop()
+                    .ok_or_else(|| anyhow!("{}: nothing to pop", context()))?;
+            }
+            ".globl" => {}
+            ".balign" => {
+                let alignment: u32 = operands[0].parse().with_context(context)?;
+                cursor = cursor.next_multiple_of(alignment);
+            }
+            ".zero" => cursor += operands[0].parse::<u32>().with_context(context)?,
+            ".half" => {
+                let value: i16 = operands[0].parse().with_context(context)?;
+                let address = cursor as usize;
+                program.memory[address..address + 2].copy_from_slice(&value.to_le_bytes());
+                cursor += 2;
+            }
+            _ if mnemonic.starts_with('.') => {
+                return Err(anyhow!("{}: unknown directive", context()));
+            }
+            _ => program.instructions.push(Instruction {
+                mnemonic: mnemonic.to_string(),
+                operands,
+            }),
+        }
+    }
+
+    if cursor as usize >= MEMORY_SIZE / 2 {
+        return Err(anyhow!("data does not fit in memory"));
+    }
+    Ok(program)
+}
+
+impl Program {
+    pub fn read_half(&self, address: u32) -> i16 {
+        let address = address as usize;
+        i16::from_le_bytes([self.memory[address], self.memory[address + 1]])
+    }
+
+    pub fn write_half(&mut self, address: u32, value: i16) {
+        let address = address as usize;
+        self.memory[address..address + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    /// Calls the function at `label` and runs until it returns.
+    pub fn call(&mut self, label: &str) -> Result<()> {
+        let mut registers = [0u32; 32];
+        registers[register("ra")?] = RETURN_SENTINEL;
+        registers[register("sp")?] = MEMORY_SIZE as u32;
+        let mut pc = *self
+            .labels
+            .get(label)
+            .ok_or_else(|| anyhow!("Unknown label: {}", label))?;
+
+        for _ in 0..MAX_STEPS {
+            let instruction = self
+                .instructions
+                .get(pc)
+                .ok_or_else(|| anyhow!("Ran past the end of the program"))?;
+            pc += 1;
+
+            let operands = &instruction.operands;
+            let reg = |index: usize| -> Result<usize> {
+                register(
+                    operands
+                        .get(index)
+                        .ok_or_else(|| anyhow!("missing operand"))?,
+                )
+            };
+            let immediate = |index: usize| -> Result<i32> {
+                let operand = operands
+                    .get(index)
+                    .ok_or_else(|| anyhow!("missing operand"))?;
+                operand
+                    .parse()
+                    .with_context(|| format!("invalid immediate {}", operand))
+            };
+            let value = |index: usize| -> Result<u32> { Ok(registers[reg(index)?]) };
+            let result = match instruction.mnemonic.as_str() {
+                "li" => Some(immediate(1)? as u32),
+                "la" => Some(
+                    *self
+                        .symbols
+                        .get(&operands[1])
+                        .ok_or_else(|| anyhow!("Unknown symbol: {}", operands[1]))?,
+                ),
+                "mv" => Some(value(1)?),
+                "neg" => Some(value(1)?.wrapping_neg()),
+                "not" => Some(!value(1)?),
+                "seqz" => Some((value(1)? == 0) as u32),
+                "add" => Some(value(1)?.wrapping_add(value(2)?)),
+                "sub" => Some(value(1)?.wrapping_sub(value(2)?)),
+                "and" => Some(value(1)? & value(2)?),
+                "or" => Some(value(1)? | value(2)?),
+                "slt" => Some(((value(1)? as i32) < (value(2)? as i32)) as u32),
+                "addi" => Some(value(1)?.wrapping_add(immediate(2)? as u32)),
+                "slli" => Some(value(1)? << immediate(2)?),
+                "lh" | "lhu" | "lw" | "sh" | "sw" => {
+                    let (offset, base) = operands[1]
+                        .strip_suffix(')')
+                        .and_then(|operand| operand.split_once('('))
+                        .ok_or_else(|| anyhow!("invalid memory operand {}", operands[1]))?;
+                    let offset: i32 = offset.parse()?;
+                    let address = registers[register(base)?].wrapping_add(offset as u32) as usize;
+                    if address + 4 > MEMORY_SIZE {
+                        return Err(anyhow!("Access outside memory: {:#x}", address));
+                    }
+                    let bytes = &mut self.memory[address..];
+                    match instruction.mnemonic.as_str() {
+                        "lh" => Some(i16::from_le_bytes([bytes[0], bytes[1]]) as i32 as u32),
+                        "lhu" => Some(u16::from_le_bytes([bytes[0], bytes[1]]) as u32),
+                        "lw" => Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])),
+                        "sh" => {
+                            bytes[..2].copy_from_slice(&(value(0)? as u16).to_le_bytes());
+                            None
+                        }
+                        _ => {
+                            bytes[..4].copy_from_slice(&value(0)?.to_le_bytes());
+                            None
+                        }
+                    }
+                }
+                "ret" => {
+                    let target = registers[register("ra")?];
+                    if target == RETURN_SENTINEL {
+                        return Ok(());
+                    }
+                    pc = target as usize;
+                    None
+                }
+                other => return Err(anyhow!("Unsupported instruction: {}", other)),
+            };
+            // x0 is hardwired to zero
+            if let Some(result) = result {
+                let destination = reg(0)?;
+                if destination != 0 {
+                    registers[destination] = result;
+                }
+            }
+        }
+
+        Err(anyhow!("Program did not finish within {} steps", MAX_STEPS))
+    }
+}
+
+fn register(name: &str) -> Result<usize> {
+    let abi = [
+        "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+        "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+        "t5", "t6",
+    ];
+    abi.iter()
+        .position(|abi_name| *abi_name == name)
+        .or_else(|| name.strip_prefix('x')?.parse().ok().filter(|n| *n < 32))
+        .ok_or_else(|| anyhow!("Unknown register: {}", name))
+}
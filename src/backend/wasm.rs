@@ -0,0 +1,501 @@
+use anyhow::{Result, anyhow};
+
+use crate::{
+    backend::{Backend, SegmentAddress, segment_address},
+    command::{Command, Segment},
+    converter::Converter,
+    memory_layout::MemoryLayout,
+};
+
+/// WebAssembly text format for running programs in the browser. The Hack RAM is the first
+/// page of linear memory, exported as `memory`, with one 16-bit cell per address. Each
+/// `.vm` file becomes an exported function named after the file that runs just that file,
+/// and the exported `run` function runs every file in order. VM errors such as a stack
+/// underflow trap.
+pub struct WasmBackend;
+
+impl Backend for WasmBackend {
+    fn name(&self) -> &'static str {
+        "wasm"
+    }
+
+    fn description(&self) -> &'static str {
+        "WebAssembly text format with the Hack RAM in linear memory"
+    }
+
+    fn extension(&self) -> &'static str {
+        "wat"
+    }
+
+    fn converter(&self, file_name: &str) -> Box<dyn Converter> {
+        Box::new(WasmConverter {
+            file_name: file_name.to_string(),
+            started: false,
+        })
+    }
+
+    fn prologue(&self) -> String {
+        PROLOGUE.to_string()
+    }
+
+    fn epilogue(&self) -> String {
+        EPILOGUE.to_string()
+    }
+}
+
+// Static variables get the next free address the first time they are used, like in the
+// interpreter. The second page of memory maps each static's key, the file's position in the
+// run order and the static's index, to that address.
+const PROLOGUE: &str = r#";; Generated by vm_translator
+(module
+  (memory (export "memory") 2)
+  ;; SP starts at 256, stored little-endian
+  (data (i32.const 0) "\00\01")
+  (global $chained (mut i32) (i32.const 0))
+  (global $file_count (mut i32) (i32.const 0))
+  (global $static_count (mut i32) (i32.const 0))
+
+  (func $peek (param $address i32) (result i32)
+    (i32.load16_s (i32.shl (local.get $address) (i32.const 1))))
+
+  (func $poke (param $address i32) (param $value i32)
+    (i32.store16 (i32.shl (local.get $address) (i32.const 1)) (local.get $value)))
+
+  (func $push (param $value i32)
+    (local $sp i32)
+    (local.set $sp (i32.load16_u (i32.const 0)))
+    (if (i32.ge_u (local.get $sp) (i32.const 32768)) (then unreachable))
+    (call $poke (local.get $sp) (local.get $value))
+    (i32.store16 (i32.const 0) (i32.add (local.get $sp) (i32.const 1))))
+
+  (func $pop (result i32)
+    (local $sp i32)
+    (local.set $sp (i32.load16_u (i32.const 0)))
+    (if (i32.le_u (local.get $sp) (i32.const 256)) (then unreachable))
+    (local.set $sp (i32.sub (local.get $sp) (i32.const 1)))
+    (i32.store16 (i32.const 0) (local.get $sp))
+    (call $peek (local.get $sp)))
+
+  ;; Address of entry $index of the segment whose base is stored at $pointer
+  (func $segment (param $pointer i32) (param $index i32) (result i32)
+    (local $address i32)
+    (local.set $address
+      (i32.add (i32.load16_u (i32.shl (local.get $pointer) (i32.const 1))) (local.get $index)))
+    (if (i32.ge_u (local.get $address) (i32.const 32768)) (then unreachable))
+    (local.get $address))
+
+  (func $static (param $file i32) (param $index i32) (result i32)
+    (local $key i32)
+    (local $i i32)
+    (local.set $key (i32.or (i32.shl (local.get $file) (i32.const 16)) (local.get $index)))
+    (block $allocate
+      (loop $search
+        (br_if $allocate (i32.ge_u (local.get $i) (global.get $static_count)))
+        (if (i32.eq (i32.load (i32.add (i32.const 65536) (i32.shl (local.get $i) (i32.const 2))))
+                    (local.get $key))
+          (then (return (i32.add (i32.const 16) (local.get $i)))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $search)))
+    (if (i32.ge_u (global.get $static_count) (i32.const 240)) (then unreachable))
+    (i32.store (i32.add (i32.const 65536) (i32.shl (local.get $i) (i32.const 2))) (local.get $key))
+    (global.set $static_count (i32.add (local.get $i) (i32.const 1)))
+    (i32.add (i32.const 16) (local.get $i)))
+
+  (func $add (local $y i32)
+    (local.set $y (call $pop))
+    (call $push (i32.add (call $pop) (local.get $y))))
+
+  (func $sub (local $y i32)
+    (local.set $y (call $pop))
+    (call $push (i32.sub (call $pop) (local.get $y))))
+
+  (func $and (local $y i32)
+    (local.set $y (call $pop))
+    (call $push (i32.and (call $pop) (local.get $y))))
+
+  (func $or (local $y i32)
+    (local.set $y (call $pop))
+    (call $push (i32.or (call $pop) (local.get $y))))
+
+  (func $eq (local $y i32)
+    (local.set $y (call $pop))
+    (call $push (i32.sub (i32.const 0) (i32.eq (call $pop) (local.get $y)))))
+
+  (func $gt (local $y i32)
+    (local.set $y (call $pop))
+    (call $push (i32.sub (i32.const 0) (i32.gt_s (call $pop) (local.get $y)))))
+
+  (func $lt (local $y i32)
+    (local.set $y (call $pop))
+    (call $push (i32.sub (i32.const 0) (i32.lt_s (call $pop) (local.get $y)))))
+
+  (func $neg
+    (call $push (i32.sub (i32.const 0) (call $pop))))
+
+  (func $not
+    (call $push (i32.xor (call $pop) (i32.const -1))))
+
+  (func (export "run")
+    (global.set $chained (i32.const 1))
+    (call $files)
+    (global.set $chained (i32.const 0)))
+
+  ;; Each file's function ends by calling the next file's when called from run
+  (func $files
+"#;
+
+const EPILOGUE: &str = "  )
+)
+";
+
+struct WasmConverter {
+    file_name: String,
+    // Whether the function for this file has been opened yet
+    started: bool,
+}
+
+impl WasmConverter {
+    /// Ends the previous file's function (or `$files`, for the first file) with a call to
+    /// this file's function, and opens this file's function. The file keeps the position it
+    /// was first run in, which scopes its statics.
+    fn header(&self) -> String {
+        format!(
+            "    (if (global.get $chained) (then (call $file.{id})))\n  )\n\n\
+            \x20 (global $id.{id} (mut i32) (i32.const -1))\n\n\
+            \x20 (func $file.{id} (export {name})\n\
+            \x20   (local $file i32)\n\
+            \x20   (if (i32.lt_s (global.get $id.{id}) (i32.const 0))\n\
+            \x20     (then\n\
+            \x20       (global.set $id.{id} (global.get $file_count))\n\
+            \x20       (global.set $file_count (i32.add (global.get $file_count) (i32.const 1)))))\n\
+            \x20   (local.set $file (global.get $id.{id}))\n",
+            id = identifier(&self.file_name),
+            name = wat_string(&self.file_name)
+        )
+    }
+
+    fn address(&self, segment: Segment, index: u16) -> Result<String> {
+        let address = match segment_address(&MemoryLayout::STANDARD, segment, index)? {
+            SegmentAddress::Based { pointer, index } => format!(
+                "(call $segment (i32.const {}) (i32.const {}))",
+                pointer, index
+            ),
+            SegmentAddress::Fixed(address) => format!("(i32.const {})", address),
+            SegmentAddress::Static(index) => {
+                format!("(call $static (local.get $file) (i32.const {}))", index)
+            }
+        };
+        Ok(address)
+    }
+}
+
+impl Converter for WasmConverter {
+    fn convert(&mut self, command: Command) -> Result<String> {
+        let code = match command {
+            Command::Push {
+                segment: Segment::Constant,
+                index,
+            } => format!("(call $push (i32.const {}))", index as i16),
+            Command::Pop {
+                segment: Segment::Constant,
+                ..
+            } => return Err(anyhow!("Cannot pop constant")),
+            Command::Push { segment, index } => {
+                format!(
+                    "(call $push (call $peek {}))",
+                    self.address(segment, index)?
+                )
+            }
+            Command::Pop { segment, index } => {
+                format!("(call $poke {} (call $pop))", self.address(segment, index)?)
+            }
+            Command::Add => "(call $add)".to_string(),
+            Command::Sub => "(call $sub)".to_string(),
+            Command::And => "(call $and)".to_string(),
+            Command::Or => "(call $or)".to_string(),
+            Command::Eq => "(call $eq)".to_string(),
+            Command::Gt => "(call $gt)".to_string(),
+            Command::Lt => "(call $lt)".to_string(),
+            Command::Neg => "(call $neg)".to_string(),
+            Command::Not => "(call $not)".to_string(),
+        };
+
+        let header = if self.started {
+            String::new()
+        } else {
+            self.started = true;
+            self.header()
+        };
+        Ok(format!("{}    ;; {}\n    {}", header, command, code))
+    }
+
+    // A file without commands still gets its exported function, which does nothing
+    fn finish(&mut self) -> String {
+        if self.started {
+            return String::new();
+        }
+        self.started = true;
+        self.header()
+    }
+}
+
+/// Turns a file name into an identifier. Characters WAT does not allow in identifiers, and
+/// `%` itself, are percent-encoded, so different file names never produce the same one.
+fn identifier(file_name: &str) -> String {
+    file_name
+        .bytes()
+        .map(|byte| match byte {
+            b'%' => "%25".to_string(),
+            b'!'..=b'~' if !b"\"(),;[]{}".contains(&byte) => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Quotes `value` as a WAT string, which uses hex rather than octal escapes.
+fn wat_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for byte in value.bytes() {
+        match byte {
+            b'"' | b'\\' => {
+                quoted.push('\\');
+                quoted.push(byte as char);
+            }
+            b' '..=b'~' => quoted.push(byte as char),
+            _ => quoted.push_str(&format!("\\{:02x}", byte)),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, fs, path::Path};
+
+    use super::*;
+    use crate::{program::translate_file, test_util::TempDir};
+
+    /// A parsed S-expression. Comments are dropped and strings keep their quotes.
+    #[derive(Debug)]
+    enum Sexp {
+        Atom(String),
+        List(Vec<Sexp>),
+    }
+
+    impl Sexp {
+        fn head(&self) -> Option<&str> {
+            match self {
+                Sexp::List(items) => match items.first() {
+                    Some(Sexp::Atom(atom)) => Some(atom),
+                    _ => None,
+                },
+                Sexp::Atom(_) => None,
+            }
+        }
+
+        fn items(&self) -> &[Sexp] {
+            match self {
+                Sexp::List(items) => items,
+                Sexp::Atom(_) => &[],
+            }
+        }
+
+        fn atom(&self) -> Option<&str> {
+            match self {
+                Sexp::Atom(atom) => Some(atom),
+                Sexp::List(_) => None,
+            }
+        }
+
+        /// Every list in this expression, including itself.
+        fn lists(&self) -> Vec<&Sexp> {
+            let mut lists = vec![self];
+            for item in self.items() {
+                lists.extend(item.lists());
+            }
+            lists
+        }
+    }
+
+    fn parse(source: &str) -> Result<Sexp> {
+        let mut stack: Vec<Vec<Sexp>> = vec![Vec::new()];
+        let mut chars = source.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                ';' if chars.peek() == Some(&';') => {
+                    chars.find(|c| *c == '\n');
+                }
+                '(' => stack.push(Vec::new()),
+                ')' => {
+                    let list = stack.pop().filter(|_| !stack.is_empty());
+                    let list = list.ok_or_else(|| anyhow!("unbalanced ')'"))?;
+                    stack.last_mut().unwrap().push(Sexp::List(list));
+                }
+                '"' => {
+                    let mut string = String::from('"');
+                    loop {
+                        let c = chars.next().ok_or_else(|| anyhow!("unterminated string"))?;
+                        string.push(c);
+                        match c {
+                            '\\' => string.push(chars.next().unwrap_or_default()),
+                            '"' => break,
+                            _ => {}
+                        }
+                    }
+                    stack.last_mut().unwrap().push(Sexp::Atom(string));
+                }
+                c if c.is_whitespace() => {}
+                c => {
+                    let mut atom = c.to_string();
+                    while let Some(&next) = chars.peek() {
+                        if next.is_whitespace() || "()\";".contains(next) {
+                            break;
+                        }
+                        atom.push(next);
+                        chars.next();
+                    }
+                    stack.last_mut().unwrap().push(Sexp::Atom(atom));
+                }
+            }
+        }
+
+        let mut top = stack.pop().filter(|_| stack.is_empty());
+        let top = top.as_mut().ok_or_else(|| anyhow!("unbalanced '('"))?;
+        if top.len() != 1 {
+            return Err(anyhow!(
+                "expected a single module, found {} forms",
+                top.len()
+            ));
+        }
+        Ok(top.remove(0))
+    }
+
+    /// Checks the structure of a module: every field is one the backend uses, names are
+    /// unique, and every call and variable refers to something that is defined.
+    fn validate(source: &str) -> Result<()> {
+        let module = parse(source)?;
+        if module.head() != Some("module") {
+            return Err(anyhow!("top level form is not a module"));
+        }
+
+        let mut functions = HashSet::new();
+        let mut globals = HashSet::new();
+        let mut exports = HashSet::new();
+        for field in &module.items()[1..] {
+            let name = field.items().get(1).and_then(Sexp::atom);
+            match field.head() {
+                Some("memory" | "data") => {}
+                Some("global") => {
+                    if !globals.insert(name.unwrap_or_default()) {
+                        return Err(anyhow!("duplicate global {:?}", name));
+                    }
+                }
+                Some("func") => {
+                    if let Some(name) = name.filter(|name| name.starts_with('$'))
+                        && !functions.insert(name)
+                    {
+                        return Err(anyhow!("duplicate function {}", name));
+                    }
+                }
+                other => return Err(anyhow!("unexpected module field {:?}", other)),
+            }
+
+            for list in field.lists() {
+                let target = list.items().get(1).and_then(Sexp::atom).unwrap_or_default();
+                if list.head() == Some("export") && !exports.insert(target) {
+                    return Err(anyhow!("duplicate export {}", target));
+                }
+            }
+        }
+        if !exports.contains("\"run\"") || !exports.contains("\"memory\"") {
+            return Err(anyhow!("run and memory must be exported"));
+        }
+
+        for field in &module.items()[1..] {
+            let mut locals: HashSet<&str> = HashSet::new();
+            for list in field.lists() {
+                if matches!(list.head(), Some("param" | "local")) {
+                    locals.extend(list.items().get(1).and_then(Sexp::atom));
+                }
+            }
+
+            for list in field.lists() {
+                let target = list.items().get(1).and_then(Sexp::atom).unwrap_or_default();
+                let defined = match list.head() {
+                    Some("call") => functions.contains(target),
+                    Some("global.get" | "global.set") => globals.contains(target),
+                    Some("local.get" | "local.set") => locals.contains(target),
+                    _ => true,
+                };
+                if !defined {
+                    return Err(anyhow!("{:?} refers to undefined {}", list.head(), target));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn translate(files: &[&str]) -> String {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_programs");
+        let mut output = WasmBackend.prologue();
+        for file in files {
+            output.push_str(&translate_file(&dir.join(file), &WasmBackend).unwrap());
+        }
+        output.push_str(&WasmBackend.epilogue());
+        output
+    }
+
+    #[test]
+    fn test_programs_produce_valid_modules() {
+        validate(&translate(&[])).unwrap();
+        validate(&translate(&["BasicTest.vm"])).unwrap();
+        validate(&translate(&[
+            "SimpleAdd.vm",
+            "StaticTest.vm",
+            "PointerTest.vm",
+        ]))
+        .unwrap();
+    }
+
+    #[test]
+    fn test_each_file_is_an_exported_function() {
+        let output = translate(&["SimpleAdd.vm", "StaticTest.vm"]);
+        assert!(output.contains("(func $file.SimpleAdd (export \"SimpleAdd\")"));
+        assert!(output.contains("(func $file.StaticTest (export \"StaticTest\")"));
+        assert!(output.find("(call $file.SimpleAdd)") < output.find("(call $file.StaticTest)"));
+    }
+
+    #[test]
+    fn test_files_without_commands_are_exported() {
+        let dir = TempDir::new("wasm_empty");
+        let empty = dir.0.join("Empty.vm");
+        fs::write(&empty, "// nothing to run yet\n").unwrap();
+
+        let mut output = translate(&["SimpleAdd.vm"]);
+        output.truncate(output.len() - WasmBackend.epilogue().len());
+        output.push_str(&translate_file(&empty, &WasmBackend).unwrap());
+        output.push_str(&WasmBackend.epilogue());
+
+        validate(&output).unwrap();
+        assert!(output.contains("(func $file.Empty (export \"Empty\")"));
+        assert!(output.contains("(call $file.Empty)"));
+    }
+
+    #[test]
+    fn test_validate_rejects_broken_modules() {
+        let valid = translate(&["SimpleAdd.vm"]);
+        assert!(validate(&valid[..valid.len() - 3]).is_err());
+        assert!(validate(&valid.replace("(call $sub)", "(call $minus)")).is_err());
+        assert!(validate(&valid.replace("(export \"run\")", "")).is_err());
+    }
+
+    #[test]
+    fn test_identifiers_and_strings_are_escaped() {
+        assert_eq!(identifier("Main"), "Main");
+        assert_eq!(identifier("my file(1)"), "my%20file%281%29");
+        assert_ne!(identifier("a%20"), identifier("a "));
+        assert_eq!(wat_string("a\"é"), "\"a\\\"\\c3\\a9\"");
+    }
+}
@@ -0,0 +1,459 @@
+use std::collections::HashSet;
+
+use anyhow::{Result, anyhow};
+
+use crate::{
+    backend::{Backend, SegmentAddress, c_string, mangle, segment_address},
+    command::{Command, Segment},
+    converter::Converter,
+    memory_layout::MemoryLayout,
+};
+
+/// x86-64 GNU assembly (Intel syntax) for Linux. The Hack RAM is an array in `.bss` that
+/// `rbx` points at throughout, and a small runtime linked against libc parses `ADDRESS=VALUE`
+/// arguments and dumps the RAM in the same format as the C backend. Build with `cc`.
+pub struct X86_64Backend;
+
+impl Backend for X86_64Backend {
+    fn name(&self) -> &'static str {
+        "x86-64"
+    }
+
+    fn description(&self) -> &'static str {
+        "x86-64 GNU assembly for Linux, built with the system assembler and linker"
+    }
+
+    fn extension(&self) -> &'static str {
+        "s"
+    }
+
+    fn converter(&self, file_name: &str) -> Box<dyn Converter> {
+        Box::new(X86_64Converter {
+            file_name: file_name.to_string(),
+            statics: HashSet::new(),
+        })
+    }
+
+    fn prologue(&self) -> String {
+        PROLOGUE.to_string()
+    }
+
+    fn epilogue(&self) -> String {
+        EPILOGUE.to_string()
+    }
+}
+
+// Every VM command expands to one of these macros. Values are 16-bit, so the arithmetic
+// wraps around the same way it does on the Hack platform.
+const PROLOGUE: &str = r#"# Generated by vm_translator
+    .intel_syntax noprefix
+
+    .equ RAM_SIZE, 32768
+    .equ STATIC_BASE, 16
+    .equ STATIC_LIMIT, 256
+    .equ STACK_BASE, 256
+
+# ax = pop(), clobbers ecx
+.macro vm_pop_ax
+    movzx ecx, word ptr [rbx]
+    cmp ecx, STACK_BASE
+    jbe vm_stack_underflow
+    dec ecx
+    mov word ptr [rbx], cx
+    mov ax, word ptr [rbx + rcx*2]
+.endm
+
+# push(ax), clobbers ecx
+.macro vm_push_ax
+    movzx ecx, word ptr [rbx]
+    cmp ecx, RAM_SIZE
+    jae vm_stack_overflow
+    mov word ptr [rbx + rcx*2], ax
+    inc ecx
+    mov word ptr [rbx], cx
+.endm
+
+.macro vm_push_constant value
+    mov ax, \value
+    vm_push_ax
+.endm
+
+# rdx = address of entry `index` of the segment whose base is stored at `pointer`
+.macro vm_segment_address pointer, index
+    movzx edx, word ptr [rbx + \pointer*2]
+    add edx, \index
+    cmp edx, RAM_SIZE
+    jae vm_segment_overflow
+.endm
+
+.macro vm_push_segment pointer, index
+    vm_segment_address \pointer, \index
+    mov ax, word ptr [rbx + rdx*2]
+    vm_push_ax
+.endm
+
+.macro vm_pop_segment pointer, index
+    vm_segment_address \pointer, \index
+    vm_pop_ax
+    mov word ptr [rbx + rdx*2], ax
+.endm
+
+.macro vm_push_fixed address
+    mov ax, word ptr [rbx + \address*2]
+    vm_push_ax
+.endm
+
+.macro vm_pop_fixed address
+    vm_pop_ax
+    mov word ptr [rbx + \address*2], ax
+.endm
+
+# rdx = address of a static variable, allocated the first time it is used
+.macro vm_static_address slot, name
+    mov rdx, qword ptr [rip + \slot]
+    test rdx, rdx
+    jnz 1f
+    lea rdi, [rip + \name]
+    call vm_static_slot
+    mov qword ptr [rip + \slot], rax
+    mov rdx, rax
+1:
+.endm
+
+.macro vm_push_static slot, name
+    vm_static_address \slot, \name
+    mov ax, word ptr [rbx + rdx*2]
+    vm_push_ax
+.endm
+
+.macro vm_pop_static slot, name
+    vm_static_address \slot, \name
+    vm_pop_ax
+    mov word ptr [rbx + rdx*2], ax
+.endm
+
+# The right operand is on top of the stack, so it is popped first
+.macro vm_binary op
+    vm_pop_ax
+    mov si, ax
+    vm_pop_ax
+    \op ax, si
+    vm_push_ax
+.endm
+
+.macro vm_compare set
+    vm_pop_ax
+    mov si, ax
+    vm_pop_ax
+    cmp ax, si
+    \set al
+    movzx eax, al
+    neg eax
+    vm_push_ax
+.endm
+
+.macro vm_unary op
+    vm_pop_ax
+    \op ax
+    vm_push_ax
+.endm
+
+    .bss
+    .balign 16
+vm_ram:
+    .zero RAM_SIZE * 2
+vm_static_names:
+    .zero (STATIC_LIMIT - STATIC_BASE) * 8
+vm_static_count:
+    .zero 8
+
+    .section .rodata
+vm_stack_overflow_message:
+    .asciz "Stack overflow"
+vm_stack_underflow_message:
+    .asciz "Stack underflow"
+vm_segment_overflow_message:
+    .asciz "Segment access outside RAM"
+vm_too_many_statics_message:
+    .asciz "Too many static variables"
+vm_error_format:
+    .asciz "Error: %s\n"
+vm_assignment_format:
+    .asciz "Invalid assignment %s, expected ADDRESS=VALUE\n"
+vm_ram_format:
+    .asciz "ram %d %d\n"
+vm_static_format:
+    .asciz "static %s %d\n"
+
+    .text
+
+# Prints the message in rdi and exits with status 1
+vm_fail:
+    sub rsp, 8
+    mov rdx, rdi
+    mov rdi, qword ptr [rip + stderr@GOTPCREL]
+    mov rdi, qword ptr [rdi]
+    lea rsi, [rip + vm_error_format]
+    xor eax, eax
+    call fprintf@PLT
+    mov edi, 1
+    call exit@PLT
+
+# rax = address for the static variable named by rdi
+vm_static_slot:
+    push r12
+    push r13
+    push r14
+    mov r12, rdi
+    xor r13d, r13d
+    lea r14, [rip + vm_static_names]
+2:
+    cmp r13, qword ptr [rip + vm_static_count]
+    jae 3f
+    mov rdi, qword ptr [r14 + r13*8]
+    mov rsi, r12
+    call strcmp@PLT
+    test eax, eax
+    je 4f
+    inc r13
+    jmp 2b
+3:
+    cmp r13, STATIC_LIMIT - STATIC_BASE
+    jae vm_too_many_statics
+    mov qword ptr [r14 + r13*8], r12
+    inc qword ptr [rip + vm_static_count]
+4:
+    lea rax, [r13 + STATIC_BASE]
+    pop r14
+    pop r13
+    pop r12
+    ret
+
+vm_dump:
+    push r12
+    push r13
+    push r14
+    xor r12d, r12d
+2:
+    movsx edx, word ptr [rbx + r12*2]
+    test edx, edx
+    jz 3f
+    lea rdi, [rip + vm_ram_format]
+    mov esi, r12d
+    xor eax, eax
+    call printf@PLT
+3:
+    inc r12
+    cmp r12, RAM_SIZE
+    jb 2b
+
+    xor r12d, r12d
+    lea r13, [rip + vm_static_names]
+4:
+    cmp r12, qword ptr [rip + vm_static_count]
+    jae 5f
+    lea rdi, [rip + vm_static_format]
+    mov rsi, qword ptr [r13 + r12*8]
+    movsx edx, word ptr [rbx + r12*2 + STATIC_BASE*2]
+    xor eax, eax
+    call printf@PLT
+    inc r12
+    jmp 4b
+5:
+    pop r14
+    pop r13
+    pop r12
+    ret
+
+vm_stack_overflow:
+    lea rdi, [rip + vm_stack_overflow_message]
+    call vm_fail
+vm_stack_underflow:
+    lea rdi, [rip + vm_stack_underflow_message]
+    call vm_fail
+vm_segment_overflow:
+    lea rdi, [rip + vm_segment_overflow_message]
+    call vm_fail
+vm_too_many_statics:
+    lea rdi, [rip + vm_too_many_statics_message]
+    call vm_fail
+
+    .globl main
+main:
+    push rbx
+    push r12
+    push r13
+    push r14
+    push r15
+    sub rsp, 16
+    lea rbx, [rip + vm_ram]
+    mov word ptr [rbx], STACK_BASE
+
+    # Each argument is ADDRESS=VALUE
+    mov r12d, edi
+    mov r13, rsi
+    mov r14d, 1
+2:
+    cmp r14d, r12d
+    jge 4f
+    mov rdi, qword ptr [r13 + r14*8]
+    mov rsi, rsp
+    mov edx, 10
+    call strtol@PLT
+    mov rcx, qword ptr [rsp]
+    cmp byte ptr [rcx], '='
+    jne 3f
+    cmp rax, 0
+    jl 3f
+    cmp rax, RAM_SIZE
+    jge 3f
+    mov r15, rax
+    lea rdi, [rcx + 1]
+    xor esi, esi
+    mov edx, 10
+    call strtol@PLT
+    mov word ptr [rbx + r15*2], ax
+    inc r14d
+    jmp 2b
+3:
+    mov rdi, qword ptr [rip + stderr@GOTPCREL]
+    mov rdi, qword ptr [rdi]
+    lea rsi, [rip + vm_assignment_format]
+    mov rdx, qword ptr [r13 + r14*8]
+    xor eax, eax
+    call fprintf@PLT
+    mov eax, 2
+    jmp vm_exit
+4:
+
+"#;
+
+const EPILOGUE: &str = "
+    call vm_dump
+    xor eax, eax
+vm_exit:
+    add rsp, 16
+    pop r15
+    pop r14
+    pop r13
+    pop r12
+    pop rbx
+    ret
+
+    .section .note.GNU-stack,\"\",@progbits
+";
+
+struct X86_64Converter {
+    // Scopes static variables, so files can be concatenated without their statics colliding
+    file_name: String,
+    // Statics whose symbols have already been defined in this file's output
+    statics: HashSet<u16>,
+}
+
+impl X86_64Converter {
+    /// The macro operands for a static, defining its symbols the first time it is used.
+    fn static_operands(&mut self, index: u16) -> (String, String) {
+        let symbol = format!("vm_static.{}.{}", mangle(&self.file_name), index);
+        let operands = format!("{}, {}.name", symbol, symbol);
+
+        let definitions = if self.statics.insert(index) {
+            format!(
+                "    .pushsection .bss\n    .balign 8\n{symbol}:\n    .zero 8\n    .popsection\n\
+                \x20   .pushsection .rodata\n{symbol}.name:\n    .asciz {name}\n    .popsection\n",
+                symbol = symbol,
+                name = c_string(&format!("{}.{}", self.file_name, index))
+            )
+        } else {
+            String::new()
+        };
+        (definitions, operands)
+    }
+}
+
+impl Converter for X86_64Converter {
+    fn convert(&mut self, command: Command) -> Result<String> {
+        let mut definitions = String::new();
+        let code = match command {
+            Command::Push {
+                segment: Segment::Constant,
+                index,
+            } => format!("vm_push_constant {}", index as i16),
+            Command::Pop {
+                segment: Segment::Constant,
+                ..
+            } => return Err(anyhow!("Cannot pop constant")),
+            Command::Push { segment, index } => {
+                match segment_address(&MemoryLayout::STANDARD, segment, index)? {
+                    SegmentAddress::Based { pointer, index } => {
+                        format!("vm_push_segment {}, {}", pointer, index)
+                    }
+                    SegmentAddress::Fixed(address) => format!("vm_push_fixed {}", address),
+                    SegmentAddress::Static(index) => {
+                        let (defined, operands) = self.static_operands(index);
+                        definitions = defined;
+                        format!("vm_push_static {}", operands)
+                    }
+                }
+            }
+            Command::Pop { segment, index } => {
+                match segment_address(&MemoryLayout::STANDARD, segment, index)? {
+                    SegmentAddress::Based { pointer, index } => {
+                        format!("vm_pop_segment {}, {}", pointer, index)
+                    }
+                    SegmentAddress::Fixed(address) => format!("vm_pop_fixed {}", address),
+                    SegmentAddress::Static(index) => {
+                        let (defined, operands) = self.static_operands(index);
+                        definitions = defined;
+                        format!("vm_pop_static {}", operands)
+                    }
+                }
+            }
+            Command::Add => "vm_binary add".to_string(),
+            Command::Sub => "vm_binary sub".to_string(),
+            Command::And => "vm_binary and".to_string(),
+            Command::Or => "vm_binary or".to_string(),
+            Command::Eq => "vm_compare sete".to_string(),
+            Command::Gt => "vm_compare setg".to_string(),
+            Command::Lt => "vm_compare setl".to_string(),
+            Command::Neg => "vm_unary neg".to_string(),
+            Command::Not => "vm_unary not".to_string(),
+        };
+
+        Ok(format!("{}    # {}\n    {}", definitions, command, code))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commands_become_macros() {
+        let mut converter = X86_64Backend.converter("Main");
+        let convert = |converter: &mut Box<dyn Converter>, input: &str| {
+            converter.convert(Command::from(input).unwrap()).unwrap()
+        };
+
+        assert_eq!(
+            convert(&mut converter, "push constant 32767"),
+            "    # push constant 32767\n    vm_push_constant 32767"
+        );
+        assert_eq!(
+            convert(&mut converter, "pop temp 2"),
+            "    # pop temp 2\n    vm_pop_fixed 7"
+        );
+
+        // Symbols for a static are only defined where it is first used
+        let first = convert(&mut converter, "pop static 4");
+        assert!(first.contains("vm_static.Main.4:\n"));
+        assert!(first.ends_with("vm_pop_static vm_static.Main.4, vm_static.Main.4.name"));
+        assert!(!convert(&mut converter, "push static 4").contains("vm_static.Main.4:"));
+    }
+
+    #[test]
+    fn test_mangle_is_unambiguous() {
+        assert_eq!(mangle("Main"), "Main");
+        assert_eq!(mangle("my-file"), "my_2dfile");
+        assert_ne!(mangle("a_2d"), mangle("a-"));
+    }
+}
@@ -0,0 +1,18 @@
+use std::process::ExitCode;
+
+use lsp_server::Connection;
+
+// Language server for .vm files, speaking LSP over stdin and stdout
+fn main() -> ExitCode {
+    let (connection, io_threads) = Connection::stdio();
+    let result =
+        vm_translator::lsp::serve(connection).and_then(|()| io_threads.join().map_err(Into::into));
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
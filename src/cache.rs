@@ -0,0 +1,306 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    backend::Backend,
+    program::{Source, file_name, translate_source},
+};
+
+// Anything that changes the generated output for the same source must be part of the
+// key, otherwise a stale fragment could be reused. Changes to the backend's templates are
+// caught by translating PROBE, so they do not depend on the version being bumped.
+const CACHE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+// Uses every command, so the probe's output changes whenever any template does
+const PROBE: &str = "push constant 7\npush constant 8\nadd\npush constant 1\nsub\nneg\n\
+    push constant 2\neq\npush constant 3\ngt\npush constant 4\nlt\npush constant 5\nand\n\
+    push constant 6\nor\nnot\npop local 0\npush local 0\npop argument 1\npush argument 1\n\
+    pop static 2\npush static 2\npop pointer 0\npush pointer 0\npop this 3\npush this 3\n\
+    pop pointer 1\npush pointer 1\npop that 4\npush that 4\npop temp 5\npush temp 5\n\
+    pop local 0\n";
+
+/// Where a fragment came from.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Lookup {
+    Hit,
+    Miss,
+}
+
+/// Stores the translated fragment of each `.vm` file on disk, keyed by a hash of its
+/// contents and everything else that affects the output. Fragments are self-contained, so
+/// unchanged files can be reused as-is when a program is retranslated.
+pub struct FragmentCache<'a> {
+    dir: PathBuf,
+    backend: &'a dyn Backend,
+    // The backend's output for PROBE, followed by its fingerprint
+    output_version: String,
+}
+
+impl<'a> FragmentCache<'a> {
+    pub fn new(dir: PathBuf, backend: &'a dyn Backend) -> Self {
+        let probe = Source::from_bytes(Path::new("VmCacheProbe.vm"), PROBE.as_bytes().to_vec())
+            .and_then(|source| translate_source(source, backend))
+            .unwrap_or_else(|e| format!("error: {:#}", e));
+        let output_version = format!(
+            "{}{}{}\n{}",
+            backend.prologue(),
+            probe,
+            backend.epilogue(),
+            backend.fingerprint()
+        );
+        Self {
+            dir,
+            backend,
+            output_version,
+        }
+    }
+
+    /// The cache directory used for an input when none is given explicitly.
+    pub fn default_dir(input: &Path) -> PathBuf {
+        let base = if input.is_dir() {
+            input
+        } else {
+            input.parent().unwrap_or(Path::new("."))
+        };
+        base.join(".vm_translator_cache")
+    }
+
+    /// Returns the fragment for `path`, only running the converter if the cache has no
+    /// fragment for the file's current contents.
+    pub fn translate(&self, path: &Path) -> Result<(String, Lookup)> {
+        let content =
+            fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let file_name = file_name(path)?;
+        let target = self.backend.name();
+        let key = cache_key(target, &self.output_version, &file_name, &content);
+        let entry = self
+            .dir
+            .join(format!("{}.{:016x}.{}", file_name, key, target));
+
+        if let Ok(fragment) = fs::read_to_string(&entry) {
+            return Ok((fragment, Lookup::Hit));
+        }
+
+        let fragment = translate_source(Source::from_bytes(path, content)?, self.backend)?;
+        self.store(&file_name, &entry, &fragment)?;
+        Ok((fragment, Lookup::Miss))
+    }
+
+    fn store(&self, file_name: &str, entry: &Path, fragment: &str) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create {}", self.dir.display()))?;
+
+        // Only the latest version of each file is worth keeping for each target, along with
+        // no partial writes left by interrupted runs
+        let prefix = format!("{}.", file_name);
+        let suffix = format!(".{}", self.backend.name());
+        for old in fs::read_dir(&self.dir)? {
+            let old = old?.path();
+            let is_stale = old
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_prefix(&prefix))
+                .map(|rest| rest.strip_suffix(".tmp").unwrap_or(rest))
+                .and_then(|rest| rest.strip_suffix(&suffix))
+                .is_some_and(|key| key.len() == 16 && key.chars().all(|c| c.is_ascii_hexdigit()));
+            if is_stale {
+                fs::remove_file(&old)?;
+            }
+        }
+
+        // Written in full before being renamed into place, so an interrupted run cannot
+        // leave a truncated fragment that later runs would take for a hit
+        let partial = entry.with_file_name(format!(
+            "{}.tmp",
+            entry.file_name().unwrap_or_default().to_string_lossy()
+        ));
+        fs::write(&partial, fragment)
+            .with_context(|| format!("failed to write {}", partial.display()))?;
+        fs::rename(&partial, entry).with_context(|| format!("failed to write {}", entry.display()))
+    }
+}
+
+fn cache_key(target: &str, output_version: &str, file_name: &str, content: &[u8]) -> u64 {
+    let mut hash = Fnv1a::new();
+    for part in [
+        CACHE_VERSION.as_bytes(),
+        target.as_bytes(),
+        output_version.as_bytes(),
+        file_name.as_bytes(),
+    ] {
+        hash.write(part);
+        // Separator so ("ab", "c") and ("a", "bc") hash differently
+        hash.write(&[0]);
+    }
+    hash.write(content);
+    hash.finish()
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher`, its output is stable across Rust releases, which
+/// matters for keys that are persisted to disk.
+pub(crate) struct Fnv1a(u64);
+
+impl Fnv1a {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    pub(crate) fn new() -> Self {
+        Self(Self::OFFSET_BASIS)
+    }
+
+    pub(crate) fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::{default_backend, hack::HackBackend},
+        labels::LabelScheme,
+        memory_layout::MemoryLayout,
+        test_util::TempDir,
+    };
+
+    fn cache_entries(dir: &Path) -> Vec<String> {
+        let mut entries: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        entries.sort();
+        entries
+    }
+
+    #[test]
+    fn test_unchanged_files_are_reused() {
+        let dir = TempDir::new("cache_reuse");
+        let source = dir.0.join("Main.vm");
+        fs::write(&source, "push constant 1\npush constant 2\neq\n").unwrap();
+        let cache = FragmentCache::new(dir.0.join("cache"), default_backend());
+
+        let (first, lookup) = cache.translate(&source).unwrap();
+        assert_eq!(lookup, Lookup::Miss);
+        assert!(first.contains("(Main.IsEqual.1)"));
+
+        let (second, lookup) = cache.translate(&source).unwrap();
+        assert_eq!(lookup, Lookup::Hit);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_changed_files_replace_their_entry() {
+        let dir = TempDir::new("cache_change");
+        let main = dir.0.join("Main.vm");
+        let other = dir.0.join("Other.vm");
+        fs::write(&main, "push constant 1\n").unwrap();
+        fs::write(&other, "push constant 1\n").unwrap();
+        let cache = FragmentCache::new(dir.0.join("cache"), default_backend());
+
+        cache.translate(&main).unwrap();
+        cache.translate(&other).unwrap();
+        assert_eq!(cache_entries(&dir.0.join("cache")).len(), 2);
+
+        fs::write(&main, "push constant 2\n").unwrap();
+        let (fragment, lookup) = cache.translate(&main).unwrap();
+        assert_eq!(lookup, Lookup::Miss);
+        assert!(fragment.contains("@2\n"));
+
+        let entries = cache_entries(&dir.0.join("cache"));
+        assert_eq!(
+            entries.len(),
+            2,
+            "stale entry should be removed: {:?}",
+            entries
+        );
+        assert!(entries.iter().any(|entry| entry.starts_with("Other.")));
+    }
+
+    #[test]
+    fn test_failed_translations_are_not_cached() {
+        let dir = TempDir::new("cache_failure");
+        let source = dir.0.join("Main.vm");
+        fs::write(&source, "pop constant 1\n").unwrap();
+        let cache = FragmentCache::new(dir.0.join("cache"), default_backend());
+
+        assert!(cache.translate(&source).is_err());
+        assert!(!dir.0.join("cache").exists());
+    }
+
+    #[test]
+    fn test_key_depends_on_inputs() {
+        assert_ne!(
+            cache_key("hack", "", "A", b"push constant 1\n"),
+            cache_key("other", "", "A", b"push constant 1\n")
+        );
+        assert_ne!(
+            cache_key("hack", "", "A", b"push constant 1\n"),
+            cache_key("hack", "", "B", b"push constant 1\n")
+        );
+        assert_ne!(
+            cache_key("hack", "", "A", b"push constant 1\n"),
+            cache_key("hack", "", "A", b"push constant 2\n")
+        );
+        assert_eq!(
+            cache_key("hack", "", "A", b"add\n"),
+            cache_key("hack", "", "A", b"add\n")
+        );
+    }
+
+    #[test]
+    fn test_template_changes_are_not_reused() {
+        let dir = TempDir::new("cache_templates");
+        let source = dir.0.join("Main.vm");
+        fs::write(&source, "push constant 1\npush constant 2\neq\n").unwrap();
+        FragmentCache::new(dir.0.join("cache"), default_backend())
+            .translate(&source)
+            .unwrap();
+
+        // Stands in for a build whose converter names labels differently
+        let labels = LabelScheme::parse("{file}_{kind}_{n}").unwrap();
+        let changed = HackBackend::new(MemoryLayout::STANDARD, labels);
+        let (fragment, lookup) = FragmentCache::new(dir.0.join("cache"), &changed)
+            .translate(&source)
+            .unwrap();
+        assert_eq!(lookup, Lookup::Miss);
+        assert!(fragment.contains("(Main_IsEqual_1)"));
+    }
+
+    #[test]
+    fn test_partial_writes_are_not_hits() {
+        let dir = TempDir::new("cache_partial");
+        let source = dir.0.join("Main.vm");
+        fs::write(&source, "push constant 1\n").unwrap();
+        let cache = FragmentCache::new(dir.0.join("cache"), default_backend());
+
+        // What an interrupted run leaves behind
+        let key = cache_key("hack", &cache.output_version, "Main", b"push constant 1\n");
+        fs::create_dir_all(dir.0.join("cache")).unwrap();
+        fs::write(
+            dir.0
+                .join("cache")
+                .join(format!("Main.{:016x}.hack.tmp", key)),
+            "@1\n",
+        )
+        .unwrap();
+
+        let (fragment, lookup) = cache.translate(&source).unwrap();
+        assert_eq!(lookup, Lookup::Miss);
+        assert!(fragment.contains("D=A"));
+        assert_eq!(
+            cache_entries(&dir.0.join("cache")),
+            [format!("Main.{:016x}.hack", key)]
+        );
+    }
+}
@@ -0,0 +1,153 @@
+//! Control-flow graphs of VM code, written as Graphviz DOT by `translate --emit cfg`.
+
+use std::fmt::Write;
+
+use anyhow::{Context, Result};
+
+use crate::{command::Command, program::Source};
+
+/// A run of commands that always execute together, from the first to the last.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    // Each command with its 1-based source line
+    pub commands: Vec<(usize, Command)>,
+    // Indexes of the blocks control can continue to. Empty for a block that leaves the graph.
+    pub successors: Vec<usize>,
+}
+
+/// The control-flow graph of one file's code.
+///
+/// Blocks end where control can branch. None of the supported commands branch: without
+/// `label`, `goto`, `if-goto`, `call` or `return` a file is straight-line code and forms a
+/// single block, and only an empty file has none.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    pub name: String,
+    pub blocks: Vec<BasicBlock>,
+}
+
+impl ControlFlowGraph {
+    pub fn build(name: &str, commands: Vec<(usize, Command)>) -> Self {
+        let blocks = if commands.is_empty() {
+            Vec::new()
+        } else {
+            vec![BasicBlock {
+                commands,
+                successors: Vec::new(),
+            }]
+        };
+        Self {
+            name: name.to_string(),
+            blocks,
+        }
+    }
+
+    /// Builds the graph of every source, one per file. Stops at the first command that does
+    /// not parse.
+    pub fn for_sources(sources: Vec<Source>) -> Result<Vec<Self>> {
+        sources
+            .into_iter()
+            .map(|source| {
+                let mut commands = Vec::new();
+                for result in source.lexer {
+                    let lexed_res = result.with_context(|| source.display_name.clone())?;
+                    if let Some(command) = lexed_res.command {
+                        commands.push((lexed_res.line, command));
+                    }
+                }
+                Ok(Self::build(&source.file_name, commands))
+            })
+            .collect()
+    }
+
+    /// The graph as a Graphviz `digraph`. Blocks list their commands with line numbers, and
+    /// `entry` and `exit` nodes mark where control enters and leaves.
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!("digraph {} {{\n", dot_string(&self.name));
+        dot.push_str("    node [shape=box, fontname=monospace];\n");
+        dot.push_str("    entry [shape=oval];\n    exit [shape=oval];\n");
+
+        for (index, block) in self.blocks.iter().enumerate() {
+            let label: String = block
+                .commands
+                .iter()
+                .map(|(line, command)| format!("{}: {}\\l", line, command))
+                .collect();
+            writeln!(dot, "    b{} [label=\"{}\"];", index, label).unwrap();
+        }
+
+        match self.blocks.first() {
+            Some(_) => dot.push_str("    entry -> b0;\n"),
+            None => dot.push_str("    entry -> exit;\n"),
+        }
+        for (index, block) in self.blocks.iter().enumerate() {
+            for successor in &block.successors {
+                writeln!(dot, "    b{} -> b{};", index, successor).unwrap();
+            }
+            if block.successors.is_empty() {
+                writeln!(dot, "    b{} -> exit;", index).unwrap();
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+// A DOT quoted string
+fn dot_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn graph(content: &str) -> ControlFlowGraph {
+        let source = Source::from_bytes(Path::new("Main.vm"), content.as_bytes().to_vec());
+        ControlFlowGraph::for_sources(vec![source.unwrap()])
+            .unwrap()
+            .remove(0)
+    }
+
+    #[test]
+    fn test_straight_line_code_is_one_block() {
+        let cfg = graph("// adds\npush constant 7\npush constant 8\nadd\n");
+        assert_eq!(cfg.name, "Main");
+        assert_eq!(cfg.blocks.len(), 1);
+        assert_eq!(
+            cfg.blocks[0].commands,
+            [
+                (2, Command::from("push constant 7").unwrap()),
+                (3, Command::from("push constant 8").unwrap()),
+                (4, Command::Add),
+            ]
+        );
+        assert_eq!(
+            cfg.to_dot(),
+            "digraph \"Main\" {\n\
+             \x20   node [shape=box, fontname=monospace];\n\
+             \x20   entry [shape=oval];\n\
+             \x20   exit [shape=oval];\n\
+             \x20   b0 [label=\"2: push constant 7\\l3: push constant 8\\l4: add\\l\"];\n\
+             \x20   entry -> b0;\n\
+             \x20   b0 -> exit;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_empty_file_goes_straight_to_exit() {
+        let cfg = graph("// nothing here\n");
+        assert!(cfg.blocks.is_empty());
+        assert!(cfg.to_dot().contains("    entry -> exit;\n"));
+    }
+
+    #[test]
+    fn test_names_are_escaped() {
+        let cfg = ControlFlowGraph::build("a\"b\\c", Vec::new());
+        assert!(cfg.to_dot().starts_with("digraph \"a\\\"b\\\\c\" {\n"));
+    }
+}
@@ -0,0 +1,280 @@
+use std::{fmt::Display, path::PathBuf};
+
+use clap::{
+    Args, Parser, Subcommand, ValueEnum,
+    builder::{PossibleValue, PossibleValuesParser},
+};
+
+use crate::{
+    backend::backends,
+    labels::LabelScheme,
+    lint::{Rule, RuleSelection},
+    parallel::default_jobs,
+};
+
+#[derive(Parser)]
+#[command(version, about = "Translates Hack VM code into Hack assembly")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: CliCommand,
+
+    /// Only print errors
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print extra progress information
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+
+    /// Project configuration file to use instead of the vmtranslator.toml found next to
+    /// the input or in a parent directory
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn verbosity(&self) -> Verbosity {
+        if self.quiet {
+            Verbosity::Quiet
+        } else if self.verbose {
+            Verbosity::Verbose
+        } else {
+            Verbosity::Normal
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum CliCommand {
+    /// Translate a .vm file, or a directory of them, into Hack assembly
+    Translate(TranslateArgs),
+    /// Execute a .vm file on a model of the Hack RAM and print the resulting stack
+    Run(RunArgs),
+    /// Parse and translate a .vm file, reporting every error without writing output
+    Check(InputArgs),
+    /// Print command, segment and instruction counts for a .vm file
+    Stats(InputArgs),
+    /// Rewrite .vm files in canonical form, or with --check report the ones that are not
+    Fmt(FmtArgs),
+    /// Recover the VM commands from Hack assembly generated by this translator
+    Decompile(DecompileArgs),
+    /// Warn about VM code that is valid but probably wrong
+    Lint(LintArgs),
+}
+
+#[derive(Args)]
+pub struct InputArgs {
+    /// Path to a .vm file, a directory of .vm files, or - to read from stdin
+    pub input: String,
+}
+
+impl InputArgs {
+    pub fn input(&self) -> Input {
+        let path = PathBuf::from(&self.input);
+        if self.input == "-" {
+            Input::Stdin
+        } else if path.is_dir() {
+            Input::Directory(path)
+        } else {
+            Input::File(path)
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct TranslateArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Where to write the output, - for stdout. Defaults to the configuration file's output,
+    /// or <input>.asm next to the input
+    #[arg(short, long)]
+    pub output: Option<String>,
+
+    /// Write the output to stdout, same as -o -
+    #[arg(long, conflicts_with = "output")]
+    pub stdout: bool,
+
+    /// What to write to the output
+    #[arg(long, value_enum, default_value_t = Emit::Asm)]
+    pub emit: Emit,
+
+    /// Code generator to use. Defaults to the configuration file's, or hack
+    #[arg(long, value_parser = target_parser())]
+    pub target: Option<String>,
+
+    /// Keep running and retranslate whenever a .vm file changes
+    #[arg(long, conflicts_with_all = ["stdout", "emit"])]
+    pub watch: bool,
+
+    /// How many files to translate in parallel. Defaults to the number of CPUs
+    #[arg(short, long, value_name = "N", default_value_t = default_jobs())]
+    pub jobs: usize,
+
+    /// Reuse the cached translation of .vm files that have not changed since the last run
+    #[arg(long, conflicts_with_all = ["watch", "emit"])]
+    pub incremental: bool,
+
+    /// Where --incremental keeps its cache. Defaults to .vm_translator_cache next to the input
+    #[arg(long, value_name = "DIR", requires = "incremental")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// TOML file describing the RAM layout of a modified Hack machine (hack target only)
+    #[arg(long, value_name = "FILE")]
+    pub memory_layout: Option<PathBuf>,
+
+    /// Override one memory layout setting, e.g. --layout temp_size=4 (hack target only)
+    #[arg(long = "layout", value_name = "KEY=VALUE")]
+    pub layout_overrides: Vec<String>,
+
+    /// How generated labels and variables are named, e.g. '{file}.{kind}.{n}' (hack target only)
+    #[arg(long, value_name = "TEMPLATE")]
+    pub label_scheme: Option<LabelScheme>,
+}
+
+impl TranslateArgs {
+    /// Whether Hack-only options were given
+    pub fn has_hack_options(&self) -> bool {
+        self.memory_layout.is_some()
+            || !self.layout_overrides.is_empty()
+            || self.label_scheme.is_some()
+    }
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Initialise a RAM cell before running, e.g. --set 1=300 to place LCL at 300
+    #[arg(long = "set", value_name = "ADDRESS=VALUE", value_parser = parse_assignment)]
+    pub assignments: Vec<(usize, i16)>,
+}
+
+#[derive(Args)]
+pub struct FmtArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Do not write anything, exit with 1 if any file is not formatted
+    #[arg(long)]
+    pub check: bool,
+}
+
+#[derive(Args)]
+pub struct LintArgs {
+    #[command(flatten)]
+    pub input: InputArgs,
+
+    /// Skip a rule. Can be given more than once
+    #[arg(long, value_name = "RULE")]
+    pub disable: Vec<Rule>,
+
+    /// Also run a rule that is off by default. Can be given more than once
+    #[arg(long, value_name = "RULE")]
+    pub enable: Vec<Rule>,
+
+    /// Run only this rule. Can be given more than once
+    #[arg(long, value_name = "RULE", conflicts_with_all = ["disable", "enable"])]
+    pub only: Vec<Rule>,
+}
+
+impl LintArgs {
+    pub fn selection(&self) -> RuleSelection {
+        RuleSelection {
+            only: self.only.clone(),
+            enable: self.enable.clone(),
+            disable: self.disable.clone(),
+        }
+    }
+}
+
+#[derive(Args)]
+pub struct DecompileArgs {
+    /// Path to a .asm file, or - to read from stdin
+    pub input: String,
+
+    /// Where to write the VM code. Defaults to stdout
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+}
+
+impl DecompileArgs {
+    pub fn input(&self) -> Input {
+        if self.input == "-" {
+            Input::Stdin
+        } else {
+            Input::File(PathBuf::from(&self.input))
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Emit {
+    /// Hack assembly
+    Asm,
+    /// The parsed commands with their source line numbers
+    Commands,
+    /// The parsed commands as a JSON document, see the README for its schema
+    Ast,
+    /// The control-flow graph of each file as Graphviz DOT
+    Cfg,
+}
+
+pub enum Input {
+    Stdin,
+    File(PathBuf),
+    Directory(PathBuf),
+}
+
+pub enum Output {
+    Stdout,
+    File(PathBuf),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+impl Verbosity {
+    /// Progress messages shown unless --quiet is given. Always written to stderr so stdout
+    /// can carry generated output.
+    pub fn status(self, message: impl Display) {
+        if self >= Verbosity::Normal {
+            eprintln!("{}", message);
+        }
+    }
+
+    /// Extra detail only shown with --verbose
+    pub fn detail(self, message: impl Display) {
+        if self >= Verbosity::Verbose {
+            eprintln!("{}", message);
+        }
+    }
+}
+
+fn target_parser() -> PossibleValuesParser {
+    PossibleValuesParser::new(
+        backends()
+            .iter()
+            .map(|backend| PossibleValue::new(backend.name()).help(backend.description())),
+    )
+}
+
+fn parse_assignment(input: &str) -> Result<(usize, i16), String> {
+    let (address, value) = input
+        .split_once('=')
+        .ok_or_else(|| format!("expected ADDRESS=VALUE but got {}", input))?;
+    let address = address
+        .trim()
+        .parse::<usize>()
+        .map_err(|_| format!("Invalid address: {}", address))?;
+    let value = value
+        .trim()
+        .parse::<i16>()
+        .map_err(|_| format!("Invalid value: {}", value))?;
+    Ok((address, value))
+}
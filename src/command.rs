@@ -1,9 +1,11 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 
+use serde::Serialize;
 use strum::{Display, EnumString};
 
-#[derive(Debug, Clone, Copy, EnumString, PartialEq, Eq, Display, Hash)]
+#[derive(Debug, Clone, Copy, EnumString, PartialEq, Eq, Display, Hash, Serialize)]
 #[strum(serialize_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
 pub enum Segment {
     Argument,
     Local,
@@ -15,7 +17,12 @@ pub enum Segment {
     Temp,
 }
 
-#[derive(Debug, Display, PartialEq, Eq, Hash)]
+/// The largest value `push constant` accepts, since Hack words are signed 16-bit values
+pub const MAX_CONSTANT: u16 = 32767;
+
+/// A parsed VM command. Displays as VM source text that `Command::from` parses back into
+/// the same command, e.g. `push local 3` or `add`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Command {
     Push { segment: Segment, index: u16 },
     Pop { segment: Segment, index: u16 },
@@ -31,11 +38,28 @@ pub enum Command {
 }
 
 impl Command {
+    /// The keyword the command starts with in VM source.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::Push { .. } => "push",
+            Command::Pop { .. } => "pop",
+            Command::Add => "add",
+            Command::Sub => "sub",
+            Command::Neg => "neg",
+            Command::Eq => "eq",
+            Command::Gt => "gt",
+            Command::Lt => "lt",
+            Command::And => "and",
+            Command::Or => "or",
+            Command::Not => "not",
+        }
+    }
+
     pub fn from(input: &str) -> anyhow::Result<Self> {
         let mut parts = input.split_ascii_whitespace();
         let command_str = parts.next().ok_or_else(|| anyhow::anyhow!("Empty line"))?;
 
-        match command_str {
+        let command = match command_str {
             "push" => {
                 let segment_str = parts
                     .next()
@@ -48,7 +72,14 @@ impl Command {
                 let index = index_str
                     .parse::<u16>()
                     .map_err(|_| anyhow::anyhow!("Invalid index: {}", index_str))?;
-                Ok(Command::Push { segment, index })
+                if segment == Segment::Constant && index > MAX_CONSTANT {
+                    return Err(anyhow::anyhow!(
+                        "Constant out of range: {} (expected 0 to {})",
+                        index,
+                        MAX_CONSTANT
+                    ));
+                }
+                Command::Push { segment, index }
             }
             "pop" => {
                 let segment_str = parts
@@ -62,18 +93,35 @@ impl Command {
                 let index = index_str
                     .parse::<u16>()
                     .map_err(|_| anyhow::anyhow!("Invalid index: {}", index_str))?;
-                Ok(Command::Pop { segment, index })
+                Command::Pop { segment, index }
+            }
+            "add" => Command::Add,
+            "sub" => Command::Sub,
+            "neg" => Command::Neg,
+            "eq" => Command::Eq,
+            "gt" => Command::Gt,
+            "lt" => Command::Lt,
+            "and" => Command::And,
+            "or" => Command::Or,
+            "not" => Command::Not,
+            _ => return Err(anyhow::anyhow!("Unknown command: {}", command_str)),
+        };
+
+        // Anything after the command would be lost when it is translated or reformatted
+        if let Some(token) = parts.next() {
+            return Err(anyhow::anyhow!("Unexpected token: {}", token));
+        }
+        Ok(command)
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Command::Push { segment, index } | Command::Pop { segment, index } => {
+                write!(f, "{} {} {}", self.name(), segment, index)
             }
-            "add" => Ok(Command::Add),
-            "sub" => Ok(Command::Sub),
-            "neg" => Ok(Command::Neg),
-            "eq" => Ok(Command::Eq),
-            "gt" => Ok(Command::Gt),
-            "lt" => Ok(Command::Lt),
-            "and" => Ok(Command::And),
-            "or" => Ok(Command::Or),
-            "not" => Ok(Command::Not),
-            _ => Err(anyhow::anyhow!("Unknown command: {}", command_str)),
+            _ => f.write_str(self.name()),
         }
     }
 }
@@ -174,6 +222,10 @@ mod tests {
             ("pop unknown 3", "Unknown segment"),
             ("pop local xyz", "Invalid index"),
             ("push constant 65536", "Invalid index"),
+            ("add extra", "Unexpected token: extra"),
+            ("push constant 1 junk more", "Unexpected token: junk"),
+            ("pop local 0 0", "Unexpected token: 0"),
+            ("push constant 32768", "Constant out of range: 32768"),
         ];
 
         for (input, expected_error) in test_cases {
@@ -219,19 +271,82 @@ mod tests {
     #[test]
     fn test_parse_boundary_values() {
         // Test max u16 value
-        let result = Command::from("push constant 65535");
+        let result = Command::from("push local 65535");
         assert!(result.is_ok());
         assert_eq!(
             result.unwrap(),
             Command::Push {
-                segment: Segment::Constant,
+                segment: Segment::Local,
                 index: 65535
             }
         );
 
+        // Test the largest constant, and the first one past it
+        assert_eq!(
+            Command::from("push constant 32767").unwrap(),
+            Command::Push {
+                segment: Segment::Constant,
+                index: MAX_CONSTANT
+            }
+        );
+        let result = Command::from("push constant 32768");
+        assert!(result.is_err());
+        assert!(
+            result
+                .unwrap_err()
+                .to_string()
+                .contains("Constant out of range")
+        );
+
         // Test overflow
         let result = Command::from("push constant 65536");
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("Invalid index"));
     }
+
+    #[test]
+    fn test_display_round_trips() {
+        let segments = [
+            Segment::Argument,
+            Segment::Local,
+            Segment::Static,
+            Segment::Constant,
+            Segment::This,
+            Segment::That,
+            Segment::Pointer,
+            Segment::Temp,
+        ];
+        let mut commands = vec![
+            Command::Add,
+            Command::Sub,
+            Command::Neg,
+            Command::Eq,
+            Command::Gt,
+            Command::Lt,
+            Command::And,
+            Command::Or,
+            Command::Not,
+        ];
+        for segment in segments {
+            for index in [0, 7, MAX_CONSTANT, 65535] {
+                if segment != Segment::Constant || index <= MAX_CONSTANT {
+                    commands.push(Command::Push { segment, index });
+                }
+                commands.push(Command::Pop { segment, index });
+            }
+        }
+
+        for command in commands {
+            let text = command.to_string();
+            assert_eq!(Command::from(&text).unwrap(), command, "{}", text);
+        }
+        assert_eq!(
+            Command::Push {
+                segment: Segment::Local,
+                index: 3
+            }
+            .to_string(),
+            "push local 3"
+        );
+    }
 }
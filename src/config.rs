@@ -0,0 +1,204 @@
+//! Project configuration read from `vmtranslator.toml`, so a project's options do not have
+//! to be passed on every run. Command-line flags override the file.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+
+use crate::{labels::LabelScheme, lint::RuleSelection, memory_layout::MemoryLayout};
+
+pub const CONFIG_FILE_NAME: &str = "vmtranslator.toml";
+
+/// The settings of a `vmtranslator.toml` file. Every setting is optional.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Backend for `translate`, as given to `--target`
+    pub target: Option<String>,
+    /// Where `translate` writes its output. Relative paths are resolved against the
+    /// directory of the configuration file when it is loaded.
+    pub output: Option<PathBuf>,
+    /// Only 0 is accepted: the translator has no optimization passes yet
+    pub optimization: u8,
+    /// Only `false` is accepted: bootstrap code calls the entry function, and `call` is not
+    /// supported yet
+    pub bootstrap: bool,
+    /// The function bootstrap code calls, so it can only be set together with `bootstrap`
+    pub entry: Option<String>,
+    /// Rules for `lint`
+    pub lint: RuleSelection,
+    /// Memory layout settings for the hack target, with the keys of `--memory-layout` files
+    pub memory_layout: Option<toml::Table>,
+    /// How the hack target names generated labels and variables
+    pub label_scheme: Option<LabelScheme>,
+}
+
+impl ProjectConfig {
+    /// Finds the configuration file that applies to `input`: the one in the input's
+    /// directory, or else in the closest parent directory that has one.
+    pub fn discover(input: &Path) -> Option<PathBuf> {
+        let start = if input.is_dir() {
+            input
+        } else {
+            input.parent()?
+        };
+        let start = fs::canonicalize(if start.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            start
+        })
+        .ok()?;
+        start
+            .ancestors()
+            .map(|dir| dir.join(CONFIG_FILE_NAME))
+            .find(|path| path.is_file())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let mut config: Self = toml::from_str(&text).with_context(|| path.display().to_string())?;
+        config
+            .validate()
+            .with_context(|| path.display().to_string())?;
+
+        if let (Some(output), Some(dir)) = (&config.output, path.parent()) {
+            config.output = Some(dir.join(output));
+        }
+        Ok(config)
+    }
+
+    /// The memory layout from the `[memory_layout]` table, with `overrides` applied
+    pub fn memory_layout(&self, overrides: &[String]) -> Result<MemoryLayout> {
+        let table = self.memory_layout.clone().unwrap_or_default();
+        MemoryLayout::from_table(table, overrides).context("[memory_layout]")
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.optimization != 0 {
+            return Err(anyhow!(
+                "optimization level {} is not available, the translator has no \
+                 optimizations yet so only 0 is accepted",
+                self.optimization
+            ));
+        }
+        if self.bootstrap {
+            return Err(anyhow!(
+                "bootstrap code is not supported yet, since it calls the entry function \
+                 and the translator has no call command"
+            ));
+        }
+        if let Some(entry) = &self.entry {
+            return Err(anyhow!(
+                "entry = \"{}\" needs bootstrap code, which is not supported yet",
+                entry
+            ));
+        }
+        let adjusts_defaults = !self.lint.enable.is_empty() || !self.lint.disable.is_empty();
+        if !self.lint.only.is_empty() && adjusts_defaults {
+            return Err(anyhow!(
+                "[lint] cannot set only together with enable or disable"
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lint::Rule, test_util::TempDir};
+
+    #[test]
+    fn test_discover_searches_parent_directories() {
+        let dir = TempDir::new("config_discover");
+        let nested = dir.0.join("project/src");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(nested.join("Main.vm"), "push constant 1\n").unwrap();
+        assert_eq!(ProjectConfig::discover(&nested.join("Main.vm")), None);
+
+        let config = dir.0.join("project").join(CONFIG_FILE_NAME);
+        fs::write(&config, "").unwrap();
+        let config = fs::canonicalize(config).unwrap();
+        assert_eq!(
+            ProjectConfig::discover(&nested.join("Main.vm")),
+            Some(config.clone())
+        );
+        assert_eq!(ProjectConfig::discover(&nested), Some(config));
+    }
+
+    #[test]
+    fn test_load_reads_settings() {
+        let dir = TempDir::new("config_load");
+        let path = dir.0.join(CONFIG_FILE_NAME);
+        fs::write(
+            &path,
+            "target = \"c\"\n\
+             output = \"build/Program.c\"\n\
+             optimization = 0\n\
+             bootstrap = false\n\
+             \n\
+             label_scheme = \"{file}_{kind}_{n}\"\n\
+             \n\
+             [lint]\n\
+             disable = [\"unused-static\"]\n\
+             \n\
+             [memory_layout]\n\
+             temp_base = 7\n",
+        )
+        .unwrap();
+
+        let config = ProjectConfig::load(&path).unwrap();
+        assert_eq!(config.target.as_deref(), Some("c"));
+        assert_eq!(config.output, Some(dir.0.join("build/Program.c")));
+        assert_eq!(config.lint.disable, [Rule::UnusedStatic]);
+        assert_eq!(
+            config.label_scheme.as_ref().unwrap().name("Main", "tmp", 2),
+            "Main_tmp_2"
+        );
+        assert_eq!(
+            config.memory_layout(&["temp_size=4".to_string()]).unwrap(),
+            MemoryLayout {
+                temp_base: 7,
+                temp_size: 4,
+                ..MemoryLayout::STANDARD
+            }
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_unsupported_settings() {
+        let dir = TempDir::new("config_unsupported");
+        let path = dir.0.join(CONFIG_FILE_NAME);
+        let cases = [
+            ("optimization = 2", "optimization level 2 is not available"),
+            ("bootstrap = true", "bootstrap code is not supported yet"),
+            ("entry = \"Sys.init\"", "needs bootstrap code"),
+            (
+                "[lint]\nonly = [\"pop-constant\"]\ndisable = [\"unused-static\"]",
+                "cannot set only together",
+            ),
+            (
+                "[lint]\nonly = [\"pop-constant\"]\nenable = [\"uninitialized-pointer\"]",
+                "cannot set only together",
+            ),
+            ("[lint]\nonly = [\"no-such-rule\"]", "unknown variant"),
+            ("targt = \"c\"", "unknown field"),
+            ("label_scheme = \"{kind}.{n}\"", "has no {file}"),
+        ];
+        for (text, expected) in cases {
+            fs::write(&path, text).unwrap();
+            let err = ProjectConfig::load(&path).unwrap_err();
+            assert!(
+                format!("{:#}", err).contains(expected),
+                "{}: {:#}",
+                text,
+                err
+            );
+        }
+    }
+}
@@ -2,15 +2,30 @@ use std::collections::HashMap;
 
 use anyhow::{Ok, Result, anyhow};
 
-use crate::command::{Command, Segment};
+use crate::{
+    backend::segment_address,
+    command::{Command, Segment},
+    labels::LabelScheme,
+    memory_layout::MemoryLayout,
+};
 
 pub trait Converter {
-    fn convert(self: &mut Self, command: Command) -> Result<String>;
+    fn convert(&mut self, command: Command) -> Result<String>;
+
+    /// Code that follows the file's last command, such as a definition every file needs
+    /// even when it has no commands. Empty by default.
+    fn finish(&mut self) -> String {
+        String::new()
+    }
 }
 
 pub struct HackConverter {
     type_count: HashMap<Command, u64>,
+    // Scopes static variables and generated labels, so the output of each file can be
+    // concatenated with others without symbols colliding
     file_name: String,
+    layout: MemoryLayout,
+    labels: LabelScheme,
 }
 
 fn get_segment_label(segment: &Segment) -> String {
@@ -24,38 +39,103 @@ fn get_segment_label(segment: &Segment) -> String {
     }
 }
 
+// The register `pointer idx` refers to, for an index that has been checked
+fn pointer_label(idx: u16) -> String {
+    get_segment_label(if idx == 0 {
+        &Segment::This
+    } else {
+        &Segment::That
+    })
+}
+
 impl HackConverter {
     pub fn new(file_name: String) -> Self {
+        Self::with_layout(file_name, MemoryLayout::STANDARD)
+    }
+
+    /// A converter for a Hack machine whose RAM is laid out differently.
+    pub fn with_layout(file_name: String, layout: MemoryLayout) -> Self {
+        Self::with_options(file_name, layout, LabelScheme::DEFAULT)
+    }
+
+    /// A converter with a memory layout and a naming scheme for generated symbols.
+    pub fn with_options(file_name: String, layout: MemoryLayout, labels: LabelScheme) -> Self {
         Self {
             type_count: HashMap::new(),
             file_name,
+            layout,
+            labels,
         }
     }
 
-    fn convert_push(self: &Self, segment: Segment, idx: u16) -> Result<String> {
+    // Numbers the occurrences of each exact command in the file from 1, so generated
+    // symbols are the same whenever the file is translated
+    fn next_count(&mut self, command: Command) -> u64 {
+        let count = self.type_count.entry(command).or_insert(1);
+        *count += 1;
+        *count - 1
+    }
+
+    // A generated label or variable, scoped to the file
+    fn symbol(&self, kind: &str, count: u64) -> String {
+        self.labels.name(&self.file_name, kind, count)
+    }
+
+    // The two cells `pop` keeps its value and address in: the configured scratch registers,
+    // or else variables of the file that the assembler allocates
+    fn scratch(&self, count: u64) -> (String, String) {
+        match self.layout.scratch {
+            Some([value, address]) => (register_symbol(value), register_symbol(address)),
+            None => (self.symbol("tmp", count), self.symbol("tmp2", count)),
+        }
+    }
+
+    // Replaces the predefined pointer symbols with their addresses when the layout moves them
+    fn relocate(&self, code: String) -> String {
+        let layout = &self.layout;
+        let standard = MemoryLayout::STANDARD;
+        if (layout.sp, layout.lcl, layout.arg, layout.this, layout.that)
+            == (
+                standard.sp,
+                standard.lcl,
+                standard.arg,
+                standard.this,
+                standard.that,
+            )
+        {
+            return code;
+        }
+
+        code.split_inclusive('\n')
+            .map(|line| {
+                let trimmed = line.trim();
+                match trimmed.strip_prefix('@') {
+                    Some(symbol @ ("SP" | "LCL" | "ARG" | "THIS" | "THAT")) => {
+                        line.replace(trimmed, &format!("@{}", layout.register(symbol)))
+                    }
+                    _ => line.to_string(),
+                }
+            })
+            .collect()
+    }
+
+    fn convert_push(&self, segment: Segment, idx: u16) -> Result<String> {
         match segment {
             Segment::Constant => Ok(convert_push_constant(idx)),
-            Segment::Temp => Ok(convert_push_temp(idx)),
+            Segment::Temp => {
+                segment_address(&self.layout, segment, idx)?;
+                Ok(convert_push_temp(self.layout.temp_base, idx))
+            }
             Segment::Static => Ok(convert_push_static(&self.file_name, idx)),
             Segment::Pointer => {
-                let label = match idx {
-                    0 => get_segment_label(&Segment::This),
-                    1 => get_segment_label(&Segment::That),
-                    _ => {
-                        return Err(anyhow!(
-                            "Invalid index for push pointer. Expected ( 0 , 1 ) but got {}",
-                            idx
-                        ));
-                    }
-                };
-                Ok(convert_push_pointer(label, format!("push pointer {}", idx)))
+                segment_address(&self.layout, segment, idx)?;
+                Ok(convert_push_pointer(pointer_label(idx)))
             }
             _ => {
                 let label = get_segment_label(&segment);
 
-                return Ok(format!(
-                    "//push {segment} {idx}\n\
-                    @{label}\n\
+                Ok(format!(
+                    "@{label}\n\
                     D=M\n\
                     @{idx}\n\
                     D=D+A\n\
@@ -67,74 +147,72 @@ impl HackConverter {
                     @SP\n\
                     M=M+1\n\
                     ",
-                    segment = segment.to_string(),
                     idx = idx
-                ));
+                ))
             }
         }
     }
 
-    fn convert_pop(self: &mut Self, segment: Segment, idx: u16) -> Result<String> {
+    fn convert_pop(&mut self, segment: Segment, idx: u16) -> Result<String> {
         match segment {
             Segment::Constant => Err(anyhow!("Cannot pop constant")),
-            Segment::Temp => Ok(convert_pop_temp(idx)),
+            Segment::Temp => {
+                segment_address(&self.layout, segment, idx)?;
+                let count = self.next_count(Command::Pop {
+                    segment,
+                    index: idx,
+                });
+                let (value, address) = self.scratch(count);
+                Ok(convert_pop_temp(
+                    self.layout.temp_base,
+                    idx,
+                    &value,
+                    &address,
+                ))
+            }
             Segment::Static => Ok(convert_pop_static(&self.file_name, idx)),
             Segment::Pointer => {
-                let label = match idx {
-                    0 => get_segment_label(&Segment::This),
-                    1 => get_segment_label(&Segment::That),
-                    _ => {
-                        return Err(anyhow!(
-                            "Invalid index for pop pointer. Expected ( 0 , 1 ) but got {}",
-                            idx
-                        ));
-                    }
-                };
-                Ok(convert_pop_pointer(label, format!("pop pointer {}", idx)))
+                segment_address(&self.layout, segment, idx)?;
+                Ok(convert_pop_pointer(pointer_label(idx)))
             }
             _ => {
-                let command = Command::Pop {
+                let count = self.next_count(Command::Pop {
                     segment,
                     index: idx,
-                };
-                let type_count = self.type_count.get(&command).unwrap_or(&1);
+                });
                 let label = get_segment_label(&segment);
+                let (value, address) = self.scratch(count);
 
                 let result = format!(
-                    "// pop {segment} {idx}
-                        @SP
+                    "@SP
                         M=M-1
                         A=M
                         D=M
-                        @tmp.{count}
+                        @{value}
                         M=D
                         @{label}
                         D=M
                         @{idx}
                         D=D+A
-                        @tmp2.{count}
+                        @{address}
                         M=D
-                        @tmp.{count}
+                        @{value}
                         D=M
-                        @tmp2.{count}
+                        @{address}
                         A=M
                         M=D
                         ",
-                    count = type_count,
                     label = label,
-                    idx = idx,
-                    segment = segment.to_string()
+                    idx = idx
                 );
 
-                self.type_count.insert(command, type_count + 1);
                 Ok(result)
             }
         }
     }
 
-    fn convert_add(self: &Self) -> Result<String> {
-        Ok("//add\n\
-        @SP\n\
+    fn convert_add(&self) -> Result<String> {
+        Ok("@SP\n\
         M=M-1\n\
         A=M\n\
         D=M\n\
@@ -147,9 +225,8 @@ impl HackConverter {
             .to_string())
     }
 
-    fn convert_sub(self: &Self) -> Result<String> {
-        Ok("//sub\n\
-        @SP\n\
+    fn convert_sub(&self) -> Result<String> {
+        Ok("@SP\n\
         M=M-1\n\
         A=M\n\
         D=M\n\
@@ -162,9 +239,8 @@ impl HackConverter {
             .to_string())
     }
 
-    fn convert_neg(self: &Self) -> Result<String> {
-        Ok("//neg\n\
-    	@SP\n\
+    fn convert_neg(&self) -> Result<String> {
+        Ok("@SP\n\
     	M=M-1\n\
     	A=M\n\
     	M=-M\n\
@@ -173,9 +249,8 @@ impl HackConverter {
             .to_string())
     }
 
-    fn convert_and(self: &Self) -> Result<String> {
-        Ok("//and\n\
-       @SP\n\
+    fn convert_and(&self) -> Result<String> {
+        Ok("@SP\n\
        M=M-1\n\
        A=M\n\
        D=M\n\
@@ -188,9 +263,8 @@ impl HackConverter {
             .to_string())
     }
 
-    fn convert_or(self: &Self) -> Result<String> {
-        Ok("//or\n\
-       @SP\n\
+    fn convert_or(&self) -> Result<String> {
+        Ok("@SP\n\
        M=M-1\n\
        A=M\n\
        D=M\n\
@@ -203,9 +277,8 @@ impl HackConverter {
             .to_string())
     }
 
-    fn convert_not(self: &Self) -> Result<String> {
-        Ok("//not\n\
-           	@SP\n\
+    fn convert_not(&self) -> Result<String> {
+        Ok("@SP\n\
            	M=M-1\n\
            	A=M\n\
            	M=!M\n\
@@ -215,11 +288,13 @@ impl HackConverter {
     }
 
     fn convert_eq(&mut self) -> Result<String> {
-        let current_count = self.type_count.get(&Command::Eq).unwrap_or(&1);
+        let count = self.next_count(Command::Eq);
+        let is_true = self.symbol("IsEqual", count);
+        let is_false = self.symbol("NotEqual", count);
+        let end = self.symbol("EqEnd", count);
 
         let result = format!(
-            "//eq\n\n\
-            // load the bottom value from stack\n\
+            "// load the bottom value from stack\n\
             @SP\n\
             M=M-1\n\
             A=M\n\
@@ -230,37 +305,37 @@ impl HackConverter {
             A=M\n\n\
             // subtract both numbers\n\
             D=M-D\n\
-            @IsEqual.{count}\n\
+            @{is_true}\n\
             D;JEQ\n\
-            @NotEqual.{count}\n\
+            @{is_false}\n\
             D;JNE\n\
-            (IsEqual.{count})\n\
+            ({is_true})\n\
             @SP\n\
             A=M\n\
             M=-1\n\
-            @EqEnd.{count}\n\
+            @{end}\n\
             0;JMP\n\
-            (NotEqual.{count})\n\
+            ({is_false})\n\
             @SP\n\
             A=M\n\
             M=0\n\
-            (EqEnd.{count})\n\
+            ({end})\n\
             @SP\n\
             M=M+1\n\
             ",
-            count = current_count
         );
 
-        self.type_count.insert(Command::Eq, current_count + 1);
         Ok(result)
     }
 
     fn convert_gt(&mut self) -> Result<String> {
-        let current_count = self.type_count.get(&Command::Gt).unwrap_or(&1);
+        let count = self.next_count(Command::Gt);
+        let is_true = self.symbol("IsGreaterThan", count);
+        let is_false = self.symbol("NotGreaterThan", count);
+        let end = self.symbol("GtEnd", count);
 
         let result = format!(
-            "//gt\n\
-            // load the bottom value from stack\n\
+            "// load the bottom value from stack\n\
             @SP\n\
             M=M-1\n\
             A=M\n\
@@ -271,38 +346,38 @@ impl HackConverter {
             A=M\n\
             // subtract both numbers\n\
             D=M-D\n\
-            @IsGreaterThan.{count}\n\
+            @{is_true}\n\
             D;JGT\n\
-            @NotGreaterThan.{count}\n\
+            @{is_false}\n\
             D;JLE\n\
-            (IsGreaterThan.{count})\n\
+            ({is_true})\n\
             @SP\n\
             A=M\n\
             M=-1\n\
-            @GtEnd.{count}\n\
+            @{end}\n\
             0;JMP\n\
-            (NotGreaterThan.{count})\n\
+            ({is_false})\n\
             @SP\n\
             A=M\n\
             M=0\n\
             // increase stack pointer\n\
-            (GtEnd.{count})\n\
+            ({end})\n\
             @SP\n\
             M=M+1\n\
             ",
-            count = current_count
         );
 
-        self.type_count.insert(Command::Gt, current_count + 1);
         Ok(result)
     }
 
     fn convert_lt(&mut self) -> std::result::Result<String, anyhow::Error> {
-        let current_count = self.type_count.get(&Command::Lt).unwrap_or(&1);
+        let count = self.next_count(Command::Lt);
+        let is_true = self.symbol("IsLessThan", count);
+        let is_false = self.symbol("NotLessThan", count);
+        let end = self.symbol("LtEnd", count);
 
         let result = format!(
-            "// lt\n\
-            // load the bottom value from stack\n\
+            "// load the bottom value from stack\n\
             @SP\n\
             M=M-1\n\
             A=M\n\
@@ -313,36 +388,34 @@ impl HackConverter {
             A=M\n\
             // subtract both numbers\n\
             D=M-D\n\
-            @IsLessThan.{count}\n\
+            @{is_true}\n\
             D;JLT\n\
-            @NotLessThan.{count}\n\
+            @{is_false}\n\
             D;JGE\n\
-            (IsLessThan.{count})\n\
+            ({is_true})\n\
             @SP\n\
             A=M\n\
             M=-1\n\
-            @LtEnd.{count}\n\
+            @{end}\n\
             0;JMP\n\
-            (NotLessThan.{count})\n\
+            ({is_false})\n\
             @SP\n\
             A=M\n\
             M=0\n\
             // increase stack pointer\n\
-            (LtEnd.{count})\n\
+            ({end})\n\
             @SP\n\
             M=M+1\n\
             ",
-            count = current_count
         );
 
-        self.type_count.insert(Command::Lt, current_count + 1);
         Ok(result)
     }
 }
 
 impl Converter for HackConverter {
-    fn convert(self: &mut Self, command: Command) -> Result<String> {
-        match command {
+    fn convert(&mut self, command: Command) -> Result<String> {
+        let code = match command {
             Command::Push { segment, index } => self.convert_push(segment, index),
             Command::Pop { segment, index } => self.convert_pop(segment, index),
             Command::Add => self.convert_add(),
@@ -354,14 +427,14 @@ impl Converter for HackConverter {
             Command::And => self.convert_and(),
             Command::Or => self.convert_or(),
             Command::Not => self.convert_not(),
-        }
+        }?;
+        Ok(format!("// {}\n{}", command, self.relocate(code)))
     }
 }
 
 fn convert_push_constant(idx: u16) -> String {
     format!(
-        "//push constant {arg}\n\
-        @{arg}\n\
+        "@{arg}\n\
         D=A\n\
         @SP\n\
         A=M\n\
@@ -372,10 +445,9 @@ fn convert_push_constant(idx: u16) -> String {
     )
 }
 
-fn convert_push_temp(idx: u16) -> String {
+fn convert_push_temp(base: u16, idx: u16) -> String {
     format!(
-        "//push temp {arg}\n\
-        @5\n\
+        "@{base}\n\
         D=A\n\
         @{arg}\n\
         D=D+A\n\
@@ -392,9 +464,7 @@ fn convert_push_temp(idx: u16) -> String {
 
 fn convert_push_static(name: &String, idx: u16) -> String {
     format!(
-        "
-        // push static {arg}\n\
-        @{name}.{arg}\n\
+        "@{name}.{arg}\n\
         D=M\n\
         @SP\n\
         A=M\n\
@@ -407,11 +477,9 @@ fn convert_push_static(name: &String, idx: u16) -> String {
     )
 }
 
-fn convert_push_pointer(segment_label: String, comment: String) -> String {
+fn convert_push_pointer(segment_label: String) -> String {
     format!(
-        "
-        //{comment}\n\
-        @{label}\n\
+        "@{label}\n\
         D=M\n\
         @SP\n\
         A=M\n\
@@ -419,56 +487,58 @@ fn convert_push_pointer(segment_label: String, comment: String) -> String {
         @SP\n\
         M=M+1\n\
     ",
-        comment = comment,
         label = segment_label
     )
 }
 
-fn convert_pop_pointer(segment_label: String, comment: String) -> String {
+fn convert_pop_pointer(segment_label: String) -> String {
     format!(
-        "
-        //{comment}\n\
-        @SP\n\
+        "@SP\n\
         M=M-1\n\
         A=M\n\
         D=M\n\
         @{label}\n\
         M=D\n\
         ",
-        comment = comment,
         label = segment_label
     )
 }
 
-fn convert_pop_temp(idx: u16) -> String {
+fn convert_pop_temp(base: u16, idx: u16, value: &str, address: &str) -> String {
     format!(
-        "// pop temp {idx}\n\
-            @SP\n\
+        "@SP\n\
             M=M-1\n\
             A=M\n\
             D=M\n\
-            @tmp\n\
+            @{value}\n\
             M=D\n\
-            @5 // base address for temp\n\
+            @{base} // base address for temp\n\
             D=A\n\
             @{idx}\n\
             D=D+A\n\
-            @tmp2\n\
+            @{address}\n\
             M=D\n\
-            @tmp\n\
+            @{value}\n\
             D=M\n\
-            @tmp2\n\
+            @{address}\n\
             A=M\n\
             M=D\n\
         ",
     )
 }
 
+// Operand addressing a scratch register, using the predefined R0 to R15 names where they exist
+fn register_symbol(address: u16) -> String {
+    if address < 16 {
+        format!("R{}", address)
+    } else {
+        address.to_string()
+    }
+}
+
 fn convert_pop_static(name: &String, idx: u16) -> String {
     format!(
-        "
-        // pop static {idx}\n\
-        @SP\n\
+        "@SP\n\
         M=M-1\n\
         A=M\n\
         D=M\n\
@@ -479,3 +549,62 @@ fn convert_pop_static(name: &String, idx: u16) -> String {
         arg = idx
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::golden::{assert_golden_files, golden_dir};
+
+    // One command per template: every arithmetic and logical command, and push and pop
+    // for every segment that accepts them
+    fn template_commands() -> Vec<Command> {
+        let mut commands = vec![
+            Command::Add,
+            Command::Sub,
+            Command::Neg,
+            Command::Eq,
+            Command::Gt,
+            Command::Lt,
+            Command::And,
+            Command::Or,
+            Command::Not,
+        ];
+        for segment in [
+            Segment::Constant,
+            Segment::Argument,
+            Segment::Local,
+            Segment::Static,
+            Segment::This,
+            Segment::That,
+            Segment::Pointer,
+            Segment::Temp,
+        ] {
+            let indexes: &[u16] = match segment {
+                Segment::Pointer => &[0, 1],
+                _ => &[3],
+            };
+            for &index in indexes {
+                commands.push(Command::Push { segment, index });
+                if segment != Segment::Constant {
+                    commands.push(Command::Pop { segment, index });
+                }
+            }
+        }
+        commands
+    }
+
+    #[test]
+    fn test_templates_match_golden_files() {
+        let cases: Vec<(String, String)> = template_commands()
+            .into_iter()
+            .map(|command| {
+                let name = format!("{}.asm", command.to_string().replace(' ', "_"));
+                let output = HackConverter::new("Main".to_string())
+                    .convert(command)
+                    .unwrap();
+                (name, output)
+            })
+            .collect();
+        assert_golden_files(&golden_dir("hack/commands"), &cases);
+    }
+}
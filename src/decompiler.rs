@@ -0,0 +1,395 @@
+//! Recovers VM commands from Hack assembly written by [`HackConverter`]. Instead of keeping a
+//! second copy of every instruction template, the templates are generated by running the
+//! converter on placeholder commands, so the decompiler always matches what the translator
+//! currently emits.
+
+use std::{ops::RangeInclusive, sync::LazyLock};
+
+use crate::{
+    command::{Command, Segment},
+    converter::{Converter, HackConverter},
+    memory_layout::MemoryLayout,
+};
+
+// Stand-ins passed to the converter, replaced by placeholders in the generated templates
+const FILE_SENTINEL: &str = "VmDecompilerFile";
+const INDEX_SENTINEL: u16 = 31415;
+
+// Placeholder characters in a template line. They cannot appear in assembly.
+const FILE: char = '\u{1}';
+const INDEX: char = '\u{2}';
+const COUNT: char = '\u{3}';
+
+/// One entry of a decompiled program, in the order it appears in the assembly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decompiled {
+    /// A command, with the line its first instruction is on
+    Command { command: Command, line: usize },
+    /// Instructions that do not match any template the translator produces
+    Unrecognized {
+        lines: RangeInclusive<usize>,
+        instructions: Vec<String>,
+    },
+}
+
+struct Template {
+    command: Command,
+    lines: Vec<String>,
+}
+
+static TEMPLATES: LazyLock<Vec<Template>> = LazyLock::new(templates);
+
+fn templates() -> Vec<Template> {
+    let mut commands = vec![
+        Command::Add,
+        Command::Sub,
+        Command::Neg,
+        Command::Eq,
+        Command::Gt,
+        Command::Lt,
+        Command::And,
+        Command::Or,
+        Command::Not,
+        Command::Push {
+            segment: Segment::Constant,
+            index: INDEX_SENTINEL,
+        },
+    ];
+    for segment in [
+        Segment::Argument,
+        Segment::Local,
+        Segment::Static,
+        Segment::This,
+        Segment::That,
+        Segment::Temp,
+    ] {
+        commands.push(Command::Push {
+            segment,
+            index: INDEX_SENTINEL,
+        });
+        commands.push(Command::Pop {
+            segment,
+            index: INDEX_SENTINEL,
+        });
+    }
+    // The pointer index picks a register instead of appearing in the output
+    for index in [0, 1] {
+        commands.push(Command::Push {
+            segment: Segment::Pointer,
+            index,
+        });
+        commands.push(Command::Pop {
+            segment: Segment::Pointer,
+            index,
+        });
+    }
+
+    // The generated code does not depend on the temp size, which only limits valid indexes
+    let layout = MemoryLayout {
+        temp_size: u16::MAX,
+        ..MemoryLayout::STANDARD
+    };
+    let mut templates: Vec<Template> = commands
+        .into_iter()
+        .map(|command| {
+            let assembly = HackConverter::with_layout(FILE_SENTINEL.to_string(), layout)
+                .convert(command.clone())
+                .expect("placeholder commands are valid");
+            let lines = instructions(&assembly)
+                .map(|(_, instruction)| placeholders(instruction))
+                .collect();
+            Template { command, lines }
+        })
+        .collect();
+    // Try longer templates first, so one that is a prefix of another cannot shadow it
+    templates.sort_by_key(|template| std::cmp::Reverse(template.lines.len()));
+    templates
+}
+
+// The instructions in `assembly` with their 1-based line numbers, without comments and
+// blank lines
+fn instructions(assembly: &str) -> impl Iterator<Item = (usize, &str)> {
+    assembly
+        .lines()
+        .enumerate()
+        .map(|(number, line)| {
+            let code = line.split("//").next().unwrap_or_default().trim();
+            (number + 1, code)
+        })
+        .filter(|(_, code)| !code.is_empty())
+}
+
+// Replaces the parts of a generated instruction that depend on the file name, the command's
+// index or the converter's label counter with placeholder characters
+fn placeholders(instruction: &str) -> String {
+    let mut line = instruction
+        .replace(FILE_SENTINEL, &FILE.to_string())
+        .replace(&INDEX_SENTINEL.to_string(), &INDEX.to_string());
+
+    // A fresh converter numbers labels and scratch variables from 1, always as the suffix
+    // of a symbol like `Main.tmp.1` or `Main.IsEqual.1`
+    let symbol_end = if line.starts_with('(') {
+        line.len() - 1
+    } else {
+        line.len()
+    };
+    if line.starts_with(['@', '(']) && line[..symbol_end].ends_with(".1") {
+        line.replace_range(symbol_end - 1..symbol_end, &COUNT.to_string());
+    }
+    line
+}
+
+#[derive(Clone, Default)]
+struct Captures {
+    file: Option<String>,
+    index: Option<u16>,
+    count: Option<String>,
+}
+
+impl Template {
+    // Matches the template against the start of `instructions`, returning the command with
+    // its index filled in
+    fn matches(&self, instructions: &[(usize, &str)]) -> Option<Command> {
+        if instructions.len() < self.lines.len() {
+            return None;
+        }
+        let mut captures = Captures::default();
+        for (pattern, (_, instruction)) in self.lines.iter().zip(instructions) {
+            if !match_line(pattern, instruction, &mut captures) {
+                return None;
+            }
+        }
+
+        Some(match self.command {
+            Command::Push { segment, index } if index == INDEX_SENTINEL => Command::Push {
+                segment,
+                index: captures.index?,
+            },
+            Command::Pop { segment, index } if index == INDEX_SENTINEL => Command::Pop {
+                segment,
+                index: captures.index?,
+            },
+            ref command => command.clone(),
+        })
+    }
+}
+
+// Matches one instruction against a template line. A placeholder matches the same text
+// everywhere within a command, and `captures` is left unchanged when the line does not match.
+fn match_line(pattern: &str, text: &str, captures: &mut Captures) -> bool {
+    let mut attempt = captures.clone();
+    if match_from(pattern, text, &mut attempt) {
+        *captures = attempt;
+        true
+    } else {
+        false
+    }
+}
+
+fn match_from(pattern: &str, text: &str, captures: &mut Captures) -> bool {
+    let Some(first) = pattern.chars().next() else {
+        return text.is_empty();
+    };
+    let rest = &pattern[first.len_utf8()..];
+
+    if ![FILE, INDEX, COUNT].contains(&first) {
+        return text
+            .strip_prefix(first)
+            .is_some_and(|text| match_from(rest, text, captures));
+    }
+
+    let known = match first {
+        FILE => captures.file.clone(),
+        INDEX => captures.index.map(|index| index.to_string()),
+        _ => captures.count.clone(),
+    };
+    if let Some(known) = known {
+        return text
+            .strip_prefix(known.as_str())
+            .is_some_and(|text| match_from(rest, text, captures));
+    }
+
+    // Try every candidate for the placeholder, backtracking when the rest does not match
+    for (end, _) in text.char_indices().skip(1).chain([(text.len(), ' ')]) {
+        let candidate = &text[..end];
+        let saved = captures.clone();
+        let accepted = match first {
+            FILE => {
+                captures.file = Some(candidate.to_string());
+                true
+            }
+            INDEX => match candidate.parse::<u16>() {
+                Ok(index) if index.to_string() == candidate => {
+                    captures.index = Some(index);
+                    true
+                }
+                _ => false,
+            },
+            _ => {
+                captures.count = Some(candidate.to_string());
+                candidate.bytes().all(|byte| byte.is_ascii_digit())
+            }
+        };
+        if accepted && match_from(rest, &text[end..], captures) {
+            return true;
+        }
+        *captures = saved;
+    }
+    false
+}
+
+/// Decompiles Hack assembly into the VM commands it was translated from. Instructions that do
+/// not match any template are collected into [`Decompiled::Unrecognized`] regions, and
+/// decompilation resumes at the next instruction that starts a recognized command.
+pub fn decompile(assembly: &str) -> Vec<Decompiled> {
+    let instructions: Vec<(usize, &str)> = instructions(assembly).collect();
+    let mut result = Vec::new();
+    let mut position = 0;
+
+    while position < instructions.len() {
+        let remaining = &instructions[position..];
+        let found = TEMPLATES
+            .iter()
+            .find_map(|template| Some((template.matches(remaining)?, template.lines.len())));
+
+        match found {
+            Some((command, length)) => {
+                result.push(Decompiled::Command {
+                    command,
+                    line: remaining[0].0,
+                });
+                position += length;
+            }
+            None => {
+                let (line, instruction) = remaining[0];
+                if let Some(Decompiled::Unrecognized {
+                    lines,
+                    instructions,
+                }) = result.last_mut()
+                {
+                    *lines = *lines.start()..=line;
+                    instructions.push(instruction.to_string());
+                } else {
+                    result.push(Decompiled::Unrecognized {
+                        lines: line..=line,
+                        instructions: vec![instruction.to_string()],
+                    });
+                }
+                position += 1;
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::{
+        backend::hack::HackBackend,
+        program::{Source, convert_source, vm_files},
+    };
+
+    fn commands(decompiled: &[Decompiled]) -> Vec<Command> {
+        decompiled
+            .iter()
+            .map(|entry| match entry {
+                Decompiled::Command { command, .. } => command.clone(),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_decompile_recovers_test_programs() {
+        let programs = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_programs");
+        for path in vm_files(&programs).unwrap() {
+            let mut expected = Vec::new();
+            let mut assembly = String::new();
+            convert_source(
+                Source::open(&path).unwrap(),
+                &HackBackend::STANDARD,
+                |_, command, code| {
+                    expected.push(command.clone());
+                    assembly.push_str(&code);
+                    assembly.push('\n');
+                    Ok(())
+                },
+            )
+            .unwrap();
+
+            assert_eq!(
+                commands(&decompile(&assembly)),
+                expected,
+                "{}",
+                path.display()
+            );
+        }
+    }
+
+    #[test]
+    fn test_decompile_recovers_indexes_and_repeated_commands() {
+        let source = "push constant 0\npush constant 32767\npush temp 5\npop temp 0\n\
+            push static 12\npop static 3\npush that 0\npop local 9\npop local 9\n\
+            push pointer 1\npop pointer 0\neq\neq\nlt\ngt\n";
+        let expected: Vec<Command> = source
+            .lines()
+            .map(|line| Command::from(line).unwrap())
+            .collect();
+
+        let mut converter = HackConverter::new("Main.Sub".to_string());
+        let assembly: String = expected
+            .iter()
+            .map(|command| converter.convert(command.clone()).unwrap() + "\n")
+            .collect();
+
+        let decompiled = decompile(&assembly);
+        assert_eq!(commands(&decompiled), expected);
+        assert_eq!(
+            decompiled[1],
+            Decompiled::Command {
+                command: expected[1].clone(),
+                line: 11
+            }
+        );
+    }
+
+    #[test]
+    fn test_decompile_reports_unrecognized_regions() {
+        let mut converter = HackConverter::new("Main".to_string());
+        let add = converter.convert(Command::Add).unwrap();
+        let assembly = format!("{}\n@42\nD=A\n\n// stray\n0;JMP\n{}\n", add, add);
+
+        let decompiled = decompile(&assembly);
+        assert_eq!(decompiled.len(), 3);
+        assert_eq!(
+            decompiled[1],
+            Decompiled::Unrecognized {
+                lines: 12..=16,
+                instructions: vec!["@42".to_string(), "D=A".to_string(), "0;JMP".to_string()],
+            }
+        );
+        assert_eq!(
+            decompiled[2],
+            Decompiled::Command {
+                command: Command::Add,
+                line: 18
+            }
+        );
+    }
+
+    #[test]
+    fn test_decompile_rejects_inconsistent_labels() {
+        let mut converter = HackConverter::new("Main".to_string());
+        let eq = converter.convert(Command::Eq).unwrap();
+        let broken = eq.replacen("Main.EqEnd.1", "Main.EqEnd.2", 1);
+
+        assert!(matches!(
+            decompile(&broken)[..],
+            [Decompiled::Unrecognized { .. }, ..]
+        ));
+    }
+}
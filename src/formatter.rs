@@ -0,0 +1,98 @@
+use anyhow::Result;
+
+use crate::program::Source;
+
+/// Reprints a VM source in canonical form: every command on its own line with single
+/// spaces between its parts, comments kept verbatim after `//`, at most one blank line in a
+/// row, no blank lines at the start or end, and a newline after the last line.
+pub fn format_source(source: Source) -> Result<String> {
+    let mut output = String::new();
+    let mut pending_blank = false;
+
+    for result in source.lexer {
+        let lexed_res = result?;
+        let line = match (&lexed_res.command, &lexed_res.comment) {
+            (Some(command), Some(comment)) => format!("{} //{}", command, comment),
+            (Some(command), None) => command.to_string(),
+            (None, Some(comment)) => format!("//{}", comment),
+            (None, None) => {
+                pending_blank = !output.is_empty();
+                continue;
+            }
+        };
+
+        if pending_blank {
+            output.push('\n');
+            pending_blank = false;
+        }
+        output.push_str(&line);
+        output.push('\n');
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn format(content: &str) -> Result<String> {
+        format_source(Source::from_bytes(
+            Path::new("Main.vm"),
+            content.as_bytes().to_vec(),
+        )?)
+    }
+
+    #[test]
+    fn test_format_normalizes_whitespace() {
+        let input = "  push   constant\t007  \r\n\tadd\npop  local 0";
+        assert_eq!(
+            format(input).unwrap(),
+            "push constant 7\nadd\npop local 0\n"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_comments() {
+        let input =
+            "   // Adds two numbers   \npush constant 1   //first\npush constant 2\n//\nadd";
+        assert_eq!(
+            format(input).unwrap(),
+            "// Adds two numbers\npush constant 1 //first\npush constant 2\n//\nadd\n"
+        );
+    }
+
+    #[test]
+    fn test_format_collapses_blank_lines() {
+        let input = "\n\n  \npush constant 1\n\n\n \npush constant 2\n\n\n";
+        assert_eq!(
+            format(input).unwrap(),
+            "push constant 1\n\npush constant 2\n"
+        );
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let input = "// header\n\n\npush   constant 7 // seven\n\n  neg\n";
+        let once = format(input).unwrap();
+        assert_eq!(format(&once).unwrap(), once);
+    }
+
+    #[test]
+    fn test_format_rejects_invalid_commands() {
+        let err = format("push constant 1\npush nowhere 2\n").unwrap_err();
+        assert!(err.to_string().starts_with("line 2:"), "{}", err);
+    }
+
+    #[test]
+    fn test_format_rejects_extra_tokens() {
+        let err = format("push constant 1 junk more\nadd\n").unwrap_err();
+        assert!(
+            err.to_string().contains("Unexpected token: junk"),
+            "{}",
+            err
+        );
+    }
+}
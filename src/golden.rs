@@ -0,0 +1,104 @@
+//! Golden-file snapshot tests. Generated output is compared with the expected output
+//! committed under `test_programs/golden/`. After an intended change to the output, run
+//! `UPDATE_GOLDEN=1 cargo test` to rewrite the files, and review the diff before committing.
+
+use std::{
+    collections::BTreeSet,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+const UPDATE_VAR: &str = "UPDATE_GOLDEN";
+
+/// A directory of golden files under `test_programs/golden/`.
+pub fn golden_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("test_programs/golden")
+        .join(name)
+}
+
+/// Every `.vm` file in `test_programs/`, in name order.
+pub fn test_programs() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_programs");
+    let mut programs: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "vm"))
+        .collect();
+    programs.sort();
+    assert!(!programs.is_empty(), "no test programs found");
+    programs
+}
+
+/// Checks that `dir` holds exactly one file per case, named and filled as given. Fails
+/// listing every file that differs, is missing or is no longer produced by any case.
+///
+/// With `UPDATE_GOLDEN` set, the files are rewritten to match instead, and stale ones
+/// are removed.
+pub fn assert_golden_files(dir: &Path, cases: &[(String, String)]) {
+    let update = env::var_os(UPDATE_VAR).is_some();
+    if update {
+        fs::create_dir_all(dir).unwrap();
+    }
+
+    let mut failures = Vec::new();
+    for (name, actual) in cases {
+        let path = dir.join(name);
+        match fs::read_to_string(&path) {
+            Ok(expected) if expected == *actual => {}
+            _ if update => fs::write(&path, actual).unwrap(),
+            Ok(expected) => failures.push(format!(
+                "{} differs: {}",
+                path.display(),
+                first_difference(&expected, actual)
+            )),
+            Err(_) => failures.push(format!("{} is missing", path.display())),
+        }
+    }
+
+    let names: BTreeSet<&str> = cases.iter().map(|(name, _)| name.as_str()).collect();
+    for entry in fs::read_dir(dir).into_iter().flatten() {
+        let path = entry.unwrap().path();
+        let stale = path.is_file()
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_none_or(|name| !names.contains(name));
+        if !stale {
+            continue;
+        }
+        if update {
+            fs::remove_file(&path).unwrap();
+        } else {
+            failures.push(format!("{} is not produced by any case", path.display()));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{}\nIf the new output is intended, run `{}=1 cargo test` to update the golden files",
+        failures.join("\n"),
+        UPDATE_VAR
+    );
+}
+
+// Where two outputs first differ, for the failure message
+fn first_difference(expected: &str, actual: &str) -> String {
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    for number in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(e), Some(a)) if e == a => {}
+            (None, None) => break,
+            (e, a) => {
+                return format!(
+                    "line {} expected {:?} but got {:?}",
+                    number,
+                    e.unwrap_or("end of file"),
+                    a.unwrap_or("end of file")
+                );
+            }
+        }
+    }
+    "only in line endings or the final newline".to_string()
+}
@@ -0,0 +1,332 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Result, anyhow};
+
+use crate::{
+    backend::{SegmentAddress, segment_address},
+    command::{Command, Segment},
+    memory_layout::MemoryLayout,
+};
+
+pub const RAM_SIZE: usize = 32768;
+
+const SP: usize = 0;
+const STATIC_BASE: usize = 16;
+const STATIC_LIMIT: usize = 256;
+pub const STACK_BASE: usize = 256;
+
+/// Executes VM commands directly against a model of the Hack RAM, without going through
+/// assembly. Used by the `run` subcommand to inspect what a program leaves behind.
+pub struct Interpreter {
+    ram: Vec<i16>,
+    // Static variables get consecutive addresses from 16 in order of first use, which is
+    // how the Hack assembler allocates the `<file>.<index>` symbols emitted by the converter
+    statics: HashMap<(String, u16), usize>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut ram = vec![0; RAM_SIZE];
+        ram[SP] = STACK_BASE as i16;
+        Self {
+            ram,
+            statics: HashMap::new(),
+        }
+    }
+
+    pub fn ram(&self) -> &[i16] {
+        &self.ram
+    }
+
+    /// Current value of every static variable used so far, keyed by its `<file>.<index>`
+    /// symbol.
+    pub fn statics(&self) -> BTreeMap<String, i16> {
+        self.statics
+            .iter()
+            .map(|((file, index), address)| (format!("{}.{}", file, index), self.ram[*address]))
+            .collect()
+    }
+
+    pub fn stack(&self) -> &[i16] {
+        let sp = (self.ram[SP] as u16 as usize).clamp(STACK_BASE, RAM_SIZE);
+        &self.ram[STACK_BASE..sp]
+    }
+
+    pub fn set(&mut self, address: usize, value: i16) -> Result<()> {
+        let cell = self
+            .ram
+            .get_mut(address)
+            .ok_or_else(|| anyhow!("RAM address {} out of range", address))?;
+        *cell = value;
+        Ok(())
+    }
+
+    pub fn execute(&mut self, file_name: &str, command: &Command) -> Result<()> {
+        match *command {
+            Command::Push { segment, index } => {
+                let value = match segment {
+                    Segment::Constant => index as i16,
+                    _ => {
+                        let address = self.address(file_name, segment, index)?;
+                        self.ram[address]
+                    }
+                };
+                self.push(value)
+            }
+            Command::Pop { segment, index } => {
+                if segment == Segment::Constant {
+                    return Err(anyhow!("Cannot pop constant"));
+                }
+                let address = self.address(file_name, segment, index)?;
+                let value = self.pop()?;
+                self.ram[address] = value;
+                Ok(())
+            }
+            Command::Add => self.binary(|x, y| x.wrapping_add(y)),
+            Command::Sub => self.binary(|x, y| x.wrapping_sub(y)),
+            Command::And => self.binary(|x, y| x & y),
+            Command::Or => self.binary(|x, y| x | y),
+            Command::Eq => self.binary(|x, y| truth(x == y)),
+            Command::Gt => self.binary(|x, y| truth(x > y)),
+            Command::Lt => self.binary(|x, y| truth(x < y)),
+            Command::Neg => self.unary(|x| x.wrapping_neg()),
+            Command::Not => self.unary(|x| !x),
+        }
+    }
+
+    fn address(&mut self, file_name: &str, segment: Segment, index: u16) -> Result<usize> {
+        let address = match segment_address(&MemoryLayout::STANDARD, segment, index)? {
+            SegmentAddress::Based { pointer, index } => {
+                self.ram[pointer as usize] as u16 as usize + index as usize
+            }
+            SegmentAddress::Fixed(address) => address as usize,
+            SegmentAddress::Static(index) => {
+                let next = STATIC_BASE + self.statics.len();
+                let address = *self
+                    .statics
+                    .entry((file_name.to_string(), index))
+                    .or_insert(next);
+                if address >= STATIC_LIMIT {
+                    return Err(anyhow!("Too many static variables"));
+                }
+                address
+            }
+        };
+
+        if address >= RAM_SIZE {
+            return Err(anyhow!(
+                "{} {} resolves to address {} which is outside RAM",
+                segment,
+                index,
+                address
+            ));
+        }
+        Ok(address)
+    }
+
+    fn push(&mut self, value: i16) -> Result<()> {
+        let sp = self.ram[SP] as u16 as usize;
+        if sp >= RAM_SIZE {
+            return Err(anyhow!("Stack overflow"));
+        }
+        self.ram[sp] = value;
+        self.ram[SP] = (sp + 1) as i16;
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Result<i16> {
+        let sp = self.ram[SP] as u16 as usize;
+        if sp <= STACK_BASE {
+            return Err(anyhow!("Stack underflow"));
+        }
+        self.ram[SP] = (sp - 1) as i16;
+        Ok(self.ram[sp - 1])
+    }
+
+    fn unary(&mut self, op: impl Fn(i16) -> i16) -> Result<()> {
+        let x = self.pop()?;
+        self.push(op(x))
+    }
+
+    fn binary(&mut self, op: impl Fn(i16, i16) -> i16) -> Result<()> {
+        let y = self.pop()?;
+        let x = self.pop()?;
+        self.push(op(x, y))
+    }
+}
+
+fn truth(value: bool) -> i16 {
+    if value { -1 } else { 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(program: &[Command]) -> Result<Interpreter> {
+        let mut interpreter = Interpreter::new();
+        for command in program {
+            interpreter.execute("Test", command)?;
+        }
+        Ok(interpreter)
+    }
+
+    fn push_constant(index: u16) -> Command {
+        Command::Push {
+            segment: Segment::Constant,
+            index,
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_and_comparison() {
+        let test_cases = [
+            (Command::Add, 7, 8, 15),
+            (Command::Sub, 7, 8, -1),
+            (Command::And, 12, 10, 8),
+            (Command::Or, 12, 10, 14),
+            (Command::Eq, 5, 5, -1),
+            (Command::Eq, 5, 6, 0),
+            (Command::Gt, 6, 5, -1),
+            (Command::Gt, 5, 6, 0),
+            (Command::Lt, 5, 6, -1),
+            (Command::Lt, 6, 5, 0),
+        ];
+
+        for (command, x, y, expected) in test_cases {
+            let program = [push_constant(x), push_constant(y), command.clone()];
+            let interpreter = run(&program).unwrap();
+            assert_eq!(
+                interpreter.stack(),
+                &[expected],
+                "Mismatch for: {:?}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn test_unary_operations() {
+        let interpreter = run(&[push_constant(5), Command::Neg]).unwrap();
+        assert_eq!(interpreter.stack(), &[-5]);
+
+        let interpreter = run(&[push_constant(0), Command::Not]).unwrap();
+        assert_eq!(interpreter.stack(), &[-1]);
+    }
+
+    #[test]
+    fn test_segment_addresses() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set(1, 300).unwrap();
+        interpreter.set(2, 400).unwrap();
+
+        let program = [
+            push_constant(10),
+            Command::Pop {
+                segment: Segment::Local,
+                index: 2,
+            },
+            push_constant(20),
+            Command::Pop {
+                segment: Segment::Argument,
+                index: 1,
+            },
+            push_constant(3000),
+            Command::Pop {
+                segment: Segment::Pointer,
+                index: 0,
+            },
+            push_constant(30),
+            Command::Pop {
+                segment: Segment::This,
+                index: 4,
+            },
+            push_constant(40),
+            Command::Pop {
+                segment: Segment::Temp,
+                index: 6,
+            },
+            push_constant(50),
+            Command::Pop {
+                segment: Segment::Static,
+                index: 3,
+            },
+        ];
+        for command in &program {
+            interpreter.execute("Test", command).unwrap();
+        }
+
+        let ram = interpreter.ram();
+        assert_eq!(ram[302], 10);
+        assert_eq!(ram[401], 20);
+        assert_eq!(ram[3], 3000);
+        assert_eq!(ram[3004], 30);
+        assert_eq!(ram[11], 40);
+        assert_eq!(ram[16], 50);
+        assert!(interpreter.stack().is_empty());
+    }
+
+    #[test]
+    fn test_statics_are_scoped_by_file() {
+        let mut interpreter = Interpreter::new();
+        let pop_static = Command::Pop {
+            segment: Segment::Static,
+            index: 0,
+        };
+
+        interpreter.execute("A", &push_constant(1)).unwrap();
+        interpreter.execute("A", &pop_static).unwrap();
+        interpreter.execute("B", &push_constant(2)).unwrap();
+        interpreter.execute("B", &pop_static).unwrap();
+
+        assert_eq!(interpreter.ram()[16], 1);
+        assert_eq!(interpreter.ram()[17], 2);
+    }
+
+    #[test]
+    fn test_errors() {
+        let test_cases = [
+            (vec![Command::Add], "Stack underflow"),
+            (
+                vec![
+                    push_constant(1),
+                    Command::Pop {
+                        segment: Segment::Constant,
+                        index: 0,
+                    },
+                ],
+                "Cannot pop constant",
+            ),
+            (
+                vec![Command::Push {
+                    segment: Segment::Pointer,
+                    index: 2,
+                }],
+                "Invalid index for pointer",
+            ),
+            (
+                vec![Command::Push {
+                    segment: Segment::Temp,
+                    index: 8,
+                }],
+                "Invalid index for temp",
+            ),
+        ];
+
+        for (program, expected_error) in test_cases {
+            let err = run(&program).err().expect("program should fail");
+            assert!(
+                err.to_string().contains(expected_error),
+                "Wrong error for {:?}: {}",
+                program,
+                err
+            );
+        }
+    }
+}
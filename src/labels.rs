@@ -0,0 +1,257 @@
+//! Names of the labels and variables the Hack converter generates, and a check that the
+//! translation units of a program do not define the same label.
+
+use std::{borrow::Cow, collections::HashMap, fmt, str::FromStr};
+
+use anyhow::{Result, anyhow};
+use serde::Deserialize;
+
+/// How generated symbols are named, as a template such as `{file}.{kind}.{n}`:
+///
+/// - `{file}` is the name of the file the code comes from, so files cannot collide
+/// - `{kind}` tells the symbols of one command apart, e.g. `IsEqual`, `EqEnd` or `tmp`
+/// - `{n}` counts the occurrences of the same command in the file, from 1. Every `eq` is
+///   numbered in turn, while each `pop` such as `pop local 2` has a count of its own, so
+///   `pop local 2` and `pop that 0` can both use `tmp` number 1.
+///
+/// All three must appear. The rest of the template is copied as is, and may only use
+/// characters that are valid in a Hack symbol.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct LabelScheme {
+    template: Cow<'static, str>,
+    // The template split at its placeholders, so names are built without parsing it again
+    parts: Cow<'static, [Part]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Text(Cow<'static, str>),
+    File,
+    Kind,
+    Count,
+}
+
+impl LabelScheme {
+    pub const DEFAULT: LabelScheme = LabelScheme {
+        template: Cow::Borrowed("{file}.{kind}.{n}"),
+        parts: Cow::Borrowed(&[
+            Part::File,
+            Part::Text(Cow::Borrowed(".")),
+            Part::Kind,
+            Part::Text(Cow::Borrowed(".")),
+            Part::Count,
+        ]),
+    };
+
+    pub fn parse(template: &str) -> Result<Self> {
+        let parts = parts(template)?;
+        for (placeholder, present) in [
+            (
+                "{file}",
+                parts.iter().any(|part| matches!(part, Part::File)),
+            ),
+            (
+                "{kind}",
+                parts.iter().any(|part| matches!(part, Part::Kind)),
+            ),
+            ("{n}", parts.iter().any(|part| matches!(part, Part::Count))),
+        ] {
+            if !present {
+                return Err(anyhow!("label scheme {} has no {}", template, placeholder));
+            }
+        }
+        for part in &parts {
+            if let Part::Text(text) = part
+                && let Some(c) = text
+                    .chars()
+                    .find(|&c| !(c.is_ascii_alphanumeric() || "_.$:".contains(c)))
+            {
+                return Err(anyhow!(
+                    "label scheme {} contains {:?}, which cannot appear in a Hack symbol",
+                    template,
+                    c
+                ));
+            }
+        }
+        let starts_with_digit = match &parts[0] {
+            Part::Text(text) => text.starts_with(|c: char| c.is_ascii_digit()),
+            Part::Count => true,
+            Part::File | Part::Kind => false,
+        };
+        if starts_with_digit {
+            return Err(anyhow!(
+                "label scheme {} starts with a digit, which Hack symbols cannot",
+                template
+            ));
+        }
+
+        Ok(Self {
+            template: Cow::Owned(template.to_string()),
+            parts: Cow::Owned(parts),
+        })
+    }
+
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// The symbol for the `n`th command of a kind in `file`
+    pub fn name(&self, file: &str, kind: &str, n: u64) -> String {
+        self.parts
+            .iter()
+            .map(|part| match part {
+                Part::Text(text) => Cow::Borrowed(text.as_ref()),
+                Part::File => Cow::Borrowed(file),
+                Part::Kind => Cow::Borrowed(kind),
+                Part::Count => Cow::Owned(n.to_string()),
+            })
+            .collect()
+    }
+}
+
+fn parts(template: &str) -> Result<Vec<Part>> {
+    let mut parts = Vec::new();
+    let mut rest = template;
+    while !rest.is_empty() {
+        let Some(start) = rest.find(['{', '}']) else {
+            parts.push(Part::Text(Cow::Owned(rest.to_string())));
+            break;
+        };
+        if start > 0 {
+            parts.push(Part::Text(Cow::Owned(rest[..start].to_string())));
+        }
+        let end = match rest[start..].find('}') {
+            Some(end) if rest.as_bytes()[start] == b'{' => start + end,
+            _ => return Err(anyhow!("label scheme {} has an unmatched brace", template)),
+        };
+        parts.push(match &rest[start..=end] {
+            "{file}" => Part::File,
+            "{kind}" => Part::Kind,
+            "{n}" => Part::Count,
+            "{function}" => {
+                return Err(anyhow!(
+                    "label scheme {} uses {{function}}, which needs the function command, \
+                     and that is not supported yet",
+                    template
+                ));
+            }
+            placeholder => {
+                return Err(anyhow!(
+                    "label scheme {} has an unknown placeholder {}",
+                    template,
+                    placeholder
+                ));
+            }
+        });
+        rest = &rest[end + 1..];
+    }
+    Ok(parts)
+}
+
+impl Default for LabelScheme {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl FromStr for LabelScheme {
+    type Err = anyhow::Error;
+
+    fn from_str(template: &str) -> Result<Self> {
+        Self::parse(template)
+    }
+}
+
+impl TryFrom<String> for LabelScheme {
+    type Error = anyhow::Error;
+
+    fn try_from(template: String) -> Result<Self> {
+        Self::parse(&template)
+    }
+}
+
+impl fmt::Display for LabelScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.template)
+    }
+}
+
+/// Checks that no label is defined more than once across the translation units of a
+/// program, given as each unit's name and the labels it defines. Concatenated output with
+/// a label defined twice would jump to whichever definition the assembler keeps.
+pub fn check_unique_labels<'a>(
+    units: impl IntoIterator<Item = (&'a str, Vec<String>)>,
+) -> Result<()> {
+    let mut defined_by: HashMap<String, &str> = HashMap::new();
+    for (unit, labels) in units {
+        for label in labels {
+            if let Some(first) = defined_by.insert(label.clone(), unit) {
+                return Err(if first == unit {
+                    anyhow!("label {} is defined twice in {}", label, unit)
+                } else {
+                    anyhow!("label {} is defined by both {} and {}", label, first, unit)
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_scheme() {
+        let scheme = LabelScheme::default();
+        assert_eq!(scheme.name("Main", "IsEqual", 3), "Main.IsEqual.3");
+        // The parts of DEFAULT are written out by hand, so they must match the parsed ones
+        assert_eq!(LabelScheme::parse("{file}.{kind}.{n}").unwrap(), scheme);
+    }
+
+    #[test]
+    fn test_custom_scheme() {
+        let scheme = LabelScheme::parse("vm$_{file}:{kind}_{n}").unwrap();
+        assert_eq!(scheme.name("Main", "tmp", 1), "vm$_Main:tmp_1");
+    }
+
+    #[test]
+    fn test_invalid_schemes_are_rejected() {
+        let cases = [
+            ("{kind}.{n}", "has no {file}"),
+            ("{file}.{n}", "has no {kind}"),
+            ("{file}.{kind}", "has no {n}"),
+            ("{file}-{kind}-{n}", "'-'"),
+            ("{n}{file}{kind}", "starts with a digit"),
+            ("1{file}{kind}{n}", "starts with a digit"),
+            ("{file}.{kind}.{n", "unmatched brace"),
+            ("{file}}.{kind}.{n}", "unmatched brace"),
+            ("{file}.{label}.{n}", "unknown placeholder {label}"),
+            ("{file}.{function}.{kind}.{n}", "needs the function command"),
+        ];
+        for (template, expected) in cases {
+            let err = LabelScheme::parse(template).unwrap_err();
+            assert!(err.to_string().contains(expected), "{}: {}", template, err);
+        }
+    }
+
+    #[test]
+    fn test_duplicate_labels_are_reported() {
+        let labels = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+        assert!(
+            check_unique_labels([("A", labels(&["A.x.1"])), ("B", labels(&["B.x.1"]))]).is_ok()
+        );
+
+        let err = check_unique_labels([("A", labels(&["x.1"])), ("B", labels(&["x.1"]))]);
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "label x.1 is defined by both A and B"
+        );
+        let err = check_unique_labels([("A", labels(&["x.1", "x.1"]))]);
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "label x.1 is defined twice in A"
+        );
+    }
+}
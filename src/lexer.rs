@@ -4,19 +4,91 @@ use std::{
     path::Path,
 };
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow};
 
 use crate::command::Command;
 
-pub struct Lexer {
-    file: BufReader<File>,
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+pub struct Lexer<R> {
+    reader: R,
+    line_number: usize,
+    // Set once the reader fails, so a broken stream yields a single error instead of looping
+    finished: bool,
 }
 
-impl Lexer {
+impl Lexer<Box<dyn BufRead>> {
     pub fn new(input_path: &Path) -> Result<Self> {
-        let file = File::open(input_path)?;
-        Ok(Self {
-            file: BufReader::new(file),
+        let file = File::open(input_path)
+            .with_context(|| format!("failed to open {}", input_path.display()))?;
+        Ok(Self::from_reader(Box::new(BufReader::new(file))))
+    }
+}
+
+impl<R: BufRead> Lexer<R> {
+    pub fn from_reader(reader: R) -> Self {
+        Self {
+            reader,
+            line_number: 0,
+            finished: false,
+        }
+    }
+
+    fn lex_line(&self, mut bytes: &[u8]) -> Result<LexedResult> {
+        let line_number = self.line_number;
+
+        if line_number == 1 {
+            if bytes.starts_with(UTF16_LE_BOM) || bytes.starts_with(UTF16_BE_BOM) {
+                return Err(anyhow!(
+                    "line 1: UTF-16 encoded input is not supported, save the file as UTF-8"
+                ));
+            }
+            bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+        }
+
+        // Accept both LF and CRLF line endings
+        bytes = bytes.strip_suffix(b"\n").unwrap_or(bytes);
+        bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+
+        // A carriage return left inside the line means CR-only line endings, which would
+        // otherwise glue several commands into one line and silently drop all but the first
+        if bytes.contains(&b'\r') {
+            return Err(anyhow!(
+                "line {}: unexpected carriage return, only LF and CRLF line endings are supported",
+                line_number
+            ));
+        }
+
+        let line = std::str::from_utf8(bytes).map_err(|e| {
+            anyhow!(
+                "line {}: invalid UTF-8 at byte {}",
+                line_number,
+                e.valid_up_to() + 1
+            )
+        })?;
+        let (code, comment) = match line.split_once("//") {
+            Some((code, comment)) => (code, Some(comment.trim_end().to_string())),
+            None => (line, None),
+        };
+        let code = code.trim();
+
+        if code.is_empty() {
+            return Ok(LexedResult {
+                command: None,
+                skippable: true,
+                line: line_number,
+                comment,
+            });
+        }
+
+        let command = Command::from(code).map_err(|e| anyhow!("line {}: {}", line_number, e))?;
+        Ok(LexedResult {
+            command: Some(command),
+            skippable: false,
+            line: line_number,
+            comment,
         })
     }
 }
@@ -25,46 +97,157 @@ pub struct LexedResult {
     pub command: Option<Command>,
     // This denotes if converter should skip this entry.. For e.x. comments, empty lines
     pub skippable: bool,
+    // 1-based line number in the source this entry was read from
+    pub line: usize,
+    // Text after `//` on this line, if it has a comment
+    pub comment: Option<String>,
 }
 
-impl Iterator for Lexer {
+impl<R: BufRead> Iterator for Lexer<R> {
     type Item = Result<LexedResult>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut line = String::new();
-        let res = self.file.read_line(&mut line);
-
-        match res {
-            std::result::Result::Ok(size) => {
-                if size > 0 {
-                    let line = line.trim();
-
-                    if line.is_empty() {
-                        return Some(Ok(LexedResult {
-                            command: None,
-                            skippable: true,
-                        }));
-                    }
-
-                    if line.starts_with("//") {
-                        return Some(Ok(LexedResult {
-                            command: None,
-                            skippable: true,
-                        }));
-                    }
-
-                    let command = Command::from(line);
-
-                    let res = command.map(|cmd| LexedResult {
-                        command: Some(cmd),
-                        skippable: false,
-                    });
-                    Some(res)
-                } else {
-                    None
-                }
+        if self.finished {
+            return None;
+        }
+
+        let mut bytes = Vec::new();
+        match self.reader.read_until(b'\n', &mut bytes) {
+            Ok(0) => None,
+            Ok(_) => {
+                self.line_number += 1;
+                Some(self.lex_line(&bytes))
+            }
+            Err(e) => {
+                self.finished = true;
+                Some(Err(anyhow::Error::new(e).context(format!(
+                    "line {}: failed to read input",
+                    self.line_number + 1
+                ))))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{self, Read};
+
+    use super::*;
+    use crate::command::Segment;
+
+    fn fixture(name: &str) -> Lexer<Box<dyn BufRead>> {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("test_programs/malformed")
+            .join(name);
+        Lexer::new(&path).expect("fixture should exist")
+    }
+
+    fn commands(lexer: impl Iterator<Item = Result<LexedResult>>) -> Vec<Command> {
+        lexer
+            .map(|res| res.expect("line should lex"))
+            .filter_map(|res| res.command)
+            .collect()
+    }
+
+    fn expected_add_program() -> Vec<Command> {
+        vec![
+            Command::Push {
+                segment: Segment::Constant,
+                index: 7,
+            },
+            Command::Push {
+                segment: Segment::Constant,
+                index: 8,
+            },
+            Command::Add,
+        ]
+    }
+
+    #[test]
+    fn test_utf8_bom_is_stripped() {
+        assert_eq!(commands(fixture("Bom.vm")), expected_add_program());
+    }
+
+    #[test]
+    fn test_crlf_line_endings() {
+        assert_eq!(commands(fixture("Crlf.vm")), expected_add_program());
+    }
+
+    #[test]
+    fn test_missing_trailing_newline() {
+        assert_eq!(
+            commands(fixture("NoTrailingNewline.vm")),
+            expected_add_program()
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_reported() {
+        let results: Vec<_> = fixture("InvalidUtf8.vm").collect();
+        assert_eq!(results.len(), 4, "lexing should continue past the bad line");
+
+        let err = results[2].as_ref().err().expect("line 3 should fail");
+        assert_eq!(err.to_string(), "line 3: invalid UTF-8 at byte 16");
+        assert!(results[3].is_ok());
+    }
+
+    #[test]
+    fn test_utf16_is_rejected() {
+        let mut lexer = fixture("Utf16.vm");
+        let err = lexer.next().unwrap().err().expect("UTF-16 should fail");
+        assert!(err.to_string().contains("UTF-16"), "got: {}", err);
+    }
+
+    #[test]
+    fn test_cr_only_line_endings_are_rejected() {
+        let mut lexer = fixture("CrOnly.vm");
+        let err = lexer.next().unwrap().err().expect("CR-only should fail");
+        assert!(
+            err.to_string().contains("unexpected carriage return"),
+            "got: {}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_parse_errors_carry_line_numbers() {
+        let mut lexer = Lexer::from_reader("push constant 1\n\nfoo\n".as_bytes());
+        assert!(lexer.next().unwrap().is_ok());
+        assert!(lexer.next().unwrap().is_ok());
+
+        let err = lexer.next().unwrap().err().expect("line 3 should fail");
+        assert_eq!(err.to_string(), "line 3: Unknown command: foo");
+    }
+
+    struct FailingReader {
+        served: bool,
+    }
+
+    impl Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.served {
+                return Err(io::Error::other("disk on fire"));
             }
-            Err(_) => None,
+            self.served = true;
+            let line = b"push constant 1\n";
+            buf[..line.len()].copy_from_slice(line);
+            Ok(line.len())
         }
     }
+
+    #[test]
+    fn test_read_errors_are_propagated_once() {
+        let reader = BufReader::new(FailingReader { served: false });
+        let results: Vec<_> = Lexer::from_reader(reader).collect();
+
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        let err = results[1].as_ref().err().expect("read should fail");
+        assert_eq!(
+            format!("{:#}", err),
+            "line 2: failed to read input: disk on fire"
+        );
+        assert!(err.chain().any(|cause| cause.is::<io::Error>()));
+    }
 }
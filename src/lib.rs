@@ -0,0 +1,25 @@
+pub mod ast;
+pub mod backend;
+pub mod cache;
+pub mod cfg;
+pub mod cli;
+pub mod command;
+pub mod config;
+pub mod converter;
+pub mod decompiler;
+pub mod formatter;
+#[cfg(test)]
+mod golden;
+pub mod interpreter;
+pub mod labels;
+pub mod lexer;
+pub mod lint;
+pub mod lsp;
+pub mod memory_layout;
+pub mod parallel;
+pub mod program;
+pub mod stack_depth;
+pub mod stats;
+#[cfg(test)]
+mod test_util;
+pub mod watch;
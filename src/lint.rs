@@ -0,0 +1,318 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt,
+};
+
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use serde::Deserialize;
+use strum::{Display, EnumString};
+
+use crate::{
+    command::{Command, Segment},
+    program::Source,
+};
+
+/// A check run by the `lint` subcommand. Rules are named in kebab-case on the command line
+/// and in warnings.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Display,
+    EnumString,
+    ValueEnum,
+    Deserialize,
+)]
+#[strum(serialize_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum Rule {
+    /// `pop constant`, which has nowhere to store the value and cannot be translated
+    PopConstant,
+    /// `this` or `that` used before `pop pointer` has set the segment's base address. Off by
+    /// default, since test scripts often set the pointers before a program runs.
+    UninitializedPointer,
+    /// A static variable that is written but never read in its file
+    UnusedStatic,
+}
+
+impl Rule {
+    pub fn all() -> BTreeSet<Rule> {
+        Rule::value_variants().iter().copied().collect()
+    }
+
+    /// The rules that run unless a selection says otherwise. The others have to be enabled.
+    pub fn defaults() -> BTreeSet<Rule> {
+        Rule::all()
+            .into_iter()
+            .filter(|rule| *rule != Rule::UninitializedPointer)
+            .collect()
+    }
+}
+
+/// Which rules to run: only the listed ones, or the default rules plus the enabled ones and
+/// minus the disabled ones. Both the `lint` command line and the `[lint]` table of a project
+/// configuration file pick rules this way.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleSelection {
+    pub only: Vec<Rule>,
+    pub enable: Vec<Rule>,
+    pub disable: Vec<Rule>,
+}
+
+impl RuleSelection {
+    /// Whether no rules are listed, so the default rules run
+    pub fn is_empty(&self) -> bool {
+        self.only.is_empty() && self.enable.is_empty() && self.disable.is_empty()
+    }
+
+    pub fn rules(&self) -> BTreeSet<Rule> {
+        if !self.only.is_empty() {
+            return self.only.iter().copied().collect();
+        }
+        let mut rules = Rule::defaults();
+        rules.extend(self.enable.iter().copied());
+        for rule in &self.disable {
+            rules.remove(rule);
+        }
+        rules
+    }
+}
+
+/// A suspicious command, located by file and line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    // How the file is referred to in diagnostics
+    pub file: String,
+    pub line: usize,
+    pub rule: Rule,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: line {}: {} [{}]",
+            self.file, self.line, self.message, self.rule
+        )
+    }
+}
+
+/// Runs the enabled rules over a program. Files are checked in the order they run, since
+/// they share the `pointer` segment. Warnings are ordered by file, then line. Fails on the
+/// first command that does not parse.
+pub fn lint(sources: Vec<Source>, rules: &BTreeSet<Rule>) -> Result<Vec<Warning>> {
+    let mut warnings = Vec::new();
+    // Segments whose base address has been set by `pop pointer`, or already reported
+    let mut initialized: HashSet<Segment> = HashSet::new();
+
+    for source in sources {
+        let mut file_warnings = Vec::new();
+        let mut warn = |line, rule, message| {
+            if rules.contains(&rule) {
+                file_warnings.push(Warning {
+                    file: source.display_name.clone(),
+                    line,
+                    rule,
+                    message,
+                });
+            }
+        };
+        // First line each static is written on, and the statics that are read
+        let mut written: BTreeMap<u16, usize> = BTreeMap::new();
+        let mut read: HashSet<u16> = HashSet::new();
+
+        for result in source.lexer {
+            let lexed_res = result.with_context(|| source.display_name.clone())?;
+            let Some(command) = lexed_res.command else {
+                continue;
+            };
+            let line = lexed_res.line;
+
+            match command {
+                Command::Pop {
+                    segment: Segment::Constant,
+                    ..
+                } => warn(
+                    line,
+                    Rule::PopConstant,
+                    "pop constant has nowhere to store the value".to_string(),
+                ),
+                Command::Pop {
+                    segment: Segment::Pointer,
+                    index: index @ (0 | 1),
+                } => {
+                    initialized.insert(if index == 0 {
+                        Segment::This
+                    } else {
+                        Segment::That
+                    });
+                }
+                Command::Push {
+                    segment: segment @ (Segment::This | Segment::That),
+                    ..
+                }
+                | Command::Pop {
+                    segment: segment @ (Segment::This | Segment::That),
+                    ..
+                } => {
+                    // Reported once per segment, the first use is the one to fix
+                    if !initialized.insert(segment) {
+                        continue;
+                    }
+                    let pointer = if segment == Segment::This { 0 } else { 1 };
+                    warn(
+                        line,
+                        Rule::UninitializedPointer,
+                        format!(
+                            "{} is used before pop pointer {} sets its base address",
+                            segment, pointer
+                        ),
+                    );
+                }
+                Command::Pop {
+                    segment: Segment::Static,
+                    index,
+                } => {
+                    written.entry(index).or_insert(line);
+                }
+                Command::Push {
+                    segment: Segment::Static,
+                    index,
+                } => {
+                    read.insert(index);
+                }
+                _ => {}
+            }
+        }
+
+        for (index, line) in written {
+            if !read.contains(&index) {
+                warn(
+                    line,
+                    Rule::UnusedStatic,
+                    format!("static {} is written but never read", index),
+                );
+            }
+        }
+        file_warnings.sort_by_key(|warning| warning.line);
+        warnings.extend(file_warnings);
+    }
+
+    Ok(warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    fn source(name: &str, content: &str) -> Source {
+        Source::from_bytes(Path::new(name), content.as_bytes().to_vec()).unwrap()
+    }
+
+    fn found(warnings: &[Warning]) -> Vec<(&str, usize, Rule)> {
+        warnings
+            .iter()
+            .map(|warning| (warning.file.as_str(), warning.line, warning.rule))
+            .collect()
+    }
+
+    const PROGRAM: &str = "push constant 1\n\
+        pop static 0\n\
+        pop constant 2\n\
+        push this 0\n\
+        pop static 1\n\
+        pop static 0\n\
+        push static 1\n\
+        push constant 3000\n\
+        pop pointer 1\n\
+        push that 2\n";
+
+    #[test]
+    fn test_rules_find_suspicious_commands() {
+        let warnings = lint(vec![source("Main.vm", PROGRAM)], &Rule::all()).unwrap();
+        assert_eq!(
+            found(&warnings),
+            [
+                ("Main.vm", 2, Rule::UnusedStatic),
+                ("Main.vm", 3, Rule::PopConstant),
+                ("Main.vm", 4, Rule::UninitializedPointer),
+            ]
+        );
+        assert_eq!(
+            warnings[2].to_string(),
+            "Main.vm: line 4: this is used before pop pointer 0 sets its base address \
+             [uninitialized-pointer]"
+        );
+    }
+
+    #[test]
+    fn test_rules_can_be_disabled() {
+        let rules = BTreeSet::from([Rule::PopConstant]);
+        let warnings = lint(vec![source("Main.vm", PROGRAM)], &rules).unwrap();
+        assert_eq!(found(&warnings), [("Main.vm", 3, Rule::PopConstant)]);
+    }
+
+    #[test]
+    fn test_pointers_carry_over_between_files_but_statics_do_not() {
+        let sources = vec![
+            source(
+                "A.vm",
+                "push constant 3000\npop pointer 0\npush constant 1\npop static 0\n",
+            ),
+            source("B.vm", "push this 1\npush static 0\n"),
+        ];
+        let warnings = lint(sources, &Rule::all()).unwrap();
+        assert_eq!(found(&warnings), [("A.vm", 4, Rule::UnusedStatic)]);
+    }
+
+    #[test]
+    fn test_uninitialized_pointer_is_opt_in() {
+        let selection = RuleSelection::default();
+        assert!(!selection.rules().contains(&Rule::UninitializedPointer));
+
+        let selection = RuleSelection {
+            enable: vec![Rule::UninitializedPointer],
+            disable: vec![Rule::UnusedStatic],
+            ..RuleSelection::default()
+        };
+        assert_eq!(
+            selection.rules(),
+            BTreeSet::from([Rule::PopConstant, Rule::UninitializedPointer])
+        );
+    }
+
+    #[test]
+    fn test_bundled_programs_pass_the_default_rules() {
+        for program in crate::golden::test_programs() {
+            let warnings = lint(vec![Source::open(&program).unwrap()], &Rule::defaults()).unwrap();
+            assert!(
+                warnings.is_empty(),
+                "{}",
+                warnings
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+        }
+    }
+
+    #[test]
+    fn test_rule_names() {
+        assert_eq!(
+            Rule::UninitializedPointer.to_string(),
+            "uninitialized-pointer"
+        );
+        assert_eq!("unused-static".parse::<Rule>().unwrap(), Rule::UnusedStatic);
+    }
+}
@@ -0,0 +1,520 @@
+//! A language server for `.vm` files, run by the `vm_lsp` binary. Documents are re-analyzed
+//! in full on every change: the lexer reports parse errors, and the Hack converter reports
+//! commands that parse but cannot be translated and provides the assembly shown on hover.
+
+use std::{collections::HashMap, io::Cursor};
+
+use anyhow::Result;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, Diagnostic,
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, Hover, HoverContents, HoverParams, HoverProviderCapability,
+    MarkupContent, MarkupKind, Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Uri,
+    notification::{
+        DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+        Notification as LspNotification, PublishDiagnostics,
+    },
+    request::{Completion, HoverRequest, Request as LspRequest},
+};
+
+use crate::{
+    converter::{Converter, HackConverter},
+    lexer::Lexer,
+};
+
+const COMMANDS: &[(&str, &str)] = &[
+    (
+        "push",
+        "push segment index: push the value at segment[index]",
+    ),
+    (
+        "pop",
+        "pop segment index: pop the top value into segment[index]",
+    ),
+    ("add", "x + y"),
+    ("sub", "x - y"),
+    ("neg", "-y"),
+    ("eq", "true (-1) if x = y, else false (0)"),
+    ("gt", "true (-1) if x > y, else false (0)"),
+    ("lt", "true (-1) if x < y, else false (0)"),
+    ("and", "x & y, bitwise"),
+    ("or", "x | y, bitwise"),
+    ("not", "!y, bitwise"),
+];
+
+const SEGMENTS: &[(&str, &str)] = &[
+    ("argument", "The function's arguments, based at ARG"),
+    ("local", "The function's local variables, based at LCL"),
+    ("static", "Variables shared by all functions in the file"),
+    ("constant", "The constant 0 to 32767, push only"),
+    ("this", "The segment based at THIS"),
+    ("that", "The segment based at THAT"),
+    ("pointer", "0 is THIS and 1 is THAT"),
+    ("temp", "Eight scratch registers, RAM[5] to RAM[12]"),
+];
+
+/// Diagnostics and generated assembly for one version of a document.
+#[derive(Debug, Default)]
+pub struct Analysis {
+    pub diagnostics: Vec<Diagnostic>,
+    // Hack assembly for each line holding a command, keyed by 0-based line number
+    assembly: HashMap<u32, (Range, String)>,
+}
+
+impl Analysis {
+    pub fn new(text: &str, file_name: &str) -> Self {
+        let lines: Vec<&str> = text.lines().collect();
+        let mut analysis = Self::default();
+        let mut converter = HackConverter::new(file_name.to_string());
+
+        // The lexer yields exactly one result per line of an in-memory document
+        for (number, result) in Lexer::from_reader(Cursor::new(text.as_bytes())).enumerate() {
+            let line = number as u32;
+            let range = code_range(lines.get(number).copied().unwrap_or_default(), line);
+            let prefix = format!("line {}: ", number + 1);
+
+            let error = match result {
+                Ok(lexed_res) => match lexed_res.command {
+                    Some(command) => match converter.convert(command) {
+                        Ok(assembly) => {
+                            analysis.assembly.insert(line, (range, assembly));
+                            continue;
+                        }
+                        Err(e) => e.to_string(),
+                    },
+                    None => continue,
+                },
+                Err(e) => e.to_string(),
+            };
+            analysis.diagnostics.push(Diagnostic {
+                range,
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("vm_translator".to_string()),
+                message: error
+                    .strip_prefix(&prefix)
+                    .map(str::to_string)
+                    .unwrap_or(error),
+                ..Default::default()
+            });
+        }
+
+        analysis
+    }
+
+    /// The Hack assembly generated for the command at `position`, if there is one.
+    pub fn hover(&self, position: Position) -> Option<Hover> {
+        let (range, assembly) = self.assembly.get(&position.line)?;
+        let code: Vec<&str> = assembly
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```asm\n{}\n```", code.join("\n")),
+            }),
+            range: Some(*range),
+        })
+    }
+}
+
+/// Completions for the word being typed at `position`: command names at the start of a
+/// line, and segment names after `push` or `pop`.
+pub fn completions(text: &str, position: Position) -> Vec<CompletionItem> {
+    let line = text.lines().nth(position.line as usize).unwrap_or_default();
+    let before = prefix_utf16(line, position.character);
+    if before.contains("//") {
+        return Vec::new();
+    }
+
+    let words: Vec<&str> = before.split_ascii_whitespace().collect();
+    let typing = if before.ends_with(|c: char| c.is_ascii_whitespace()) || words.is_empty() {
+        words.len()
+    } else {
+        words.len() - 1
+    };
+
+    let items = |entries: &[(&str, &str)], kind| {
+        entries
+            .iter()
+            .map(|(label, detail)| CompletionItem {
+                label: label.to_string(),
+                kind: Some(kind),
+                detail: Some(detail.to_string()),
+                ..Default::default()
+            })
+            .collect()
+    };
+    match (typing, words.first().copied()) {
+        (0, _) => items(COMMANDS, CompletionItemKind::KEYWORD),
+        (1, Some("push")) => items(SEGMENTS, CompletionItemKind::ENUM_MEMBER),
+        (1, Some("pop")) => items(
+            &SEGMENTS
+                .iter()
+                .copied()
+                .filter(|(segment, _)| *segment != "constant")
+                .collect::<Vec<_>>(),
+            CompletionItemKind::ENUM_MEMBER,
+        ),
+        _ => Vec::new(),
+    }
+}
+
+// The part of `line` before a column counted in UTF-16 code units, as LSP positions are
+fn prefix_utf16(line: &str, character: u32) -> &str {
+    let mut units = 0;
+    for (offset, c) in line.char_indices() {
+        if units >= character as usize {
+            return &line[..offset];
+        }
+        units += c.len_utf16();
+    }
+    line
+}
+
+// The range of a line's code, without indentation or a trailing comment. Falls back to the
+// whole line when it has no code.
+fn code_range(text: &str, line: u32) -> Range {
+    let utf16_len = |text: &str| text.encode_utf16().count() as u32;
+    let code = text.split("//").next().unwrap_or_default();
+    let (start, end) = if code.trim().is_empty() {
+        (0, utf16_len(text))
+    } else {
+        let indent = code.len() - code.trim_start().len();
+        (utf16_len(&code[..indent]), utf16_len(code.trim_end()))
+    };
+    Range::new(Position::new(line, start), Position::new(line, end))
+}
+
+// Name static variables in a document are scoped under, as if it were translated from disk
+fn file_name(uri: &Uri) -> String {
+    let last = uri
+        .path()
+        .segments()
+        .next_back()
+        .and_then(|segment| segment.decode().into_string().ok())
+        .unwrap_or_default();
+    last.strip_suffix(".vm").unwrap_or(&last).to_string()
+}
+
+pub fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![" ".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+struct Document {
+    text: String,
+    analysis: Analysis,
+}
+
+/// Runs the server on `connection` until the client shuts it down.
+pub fn serve(connection: Connection) -> Result<()> {
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    // Keyed by the URI's text, since `Uri` caches parts of itself and so is a mutable key
+    let mut documents: HashMap<String, Document> = HashMap::new();
+
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let response = handle_request(&documents, request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                // One malformed notification should not take the server down with it
+                let method = notification.method.clone();
+                let (uri, text) = match handle_notification(notification) {
+                    Ok(Some(change)) => change,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        eprintln!("Ignoring {} notification: {:#}", method, e);
+                        continue;
+                    }
+                };
+                let diagnostics = match text {
+                    Some(text) => {
+                        let analysis = Analysis::new(&text, &file_name(&uri));
+                        let diagnostics = analysis.diagnostics.clone();
+                        documents.insert(uri.as_str().to_string(), Document { text, analysis });
+                        diagnostics
+                    }
+                    None => {
+                        documents.remove(uri.as_str());
+                        Vec::new()
+                    }
+                };
+                let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+                connection
+                    .sender
+                    .send(Message::Notification(Notification::new(
+                        PublishDiagnostics::METHOD.to_string(),
+                        params,
+                    )))?;
+            }
+            Message::Response(_) => {}
+        }
+    }
+    Ok(())
+}
+
+fn handle_request(documents: &HashMap<String, Document>, request: Request) -> Response {
+    let id = request.id.clone();
+    let result = match request.method.as_str() {
+        HoverRequest::METHOD => {
+            request
+                .extract::<HoverParams>(HoverRequest::METHOD)
+                .map(|(_, params)| {
+                    let position = params.text_document_position_params;
+                    let hover = documents
+                        .get(position.text_document.uri.as_str())
+                        .and_then(|document| document.analysis.hover(position.position));
+                    serde_json::to_value(hover)
+                })
+        }
+        Completion::METHOD => {
+            request
+                .extract::<CompletionParams>(Completion::METHOD)
+                .map(|(_, params)| {
+                    let position = params.text_document_position;
+                    let items = documents
+                        .get(position.text_document.uri.as_str())
+                        .map(|document| completions(&document.text, position.position))
+                        .unwrap_or_default();
+                    serde_json::to_value(items)
+                })
+        }
+        _ => {
+            return Response::new_err(
+                id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unsupported request {}", request.method),
+            );
+        }
+    };
+
+    match result {
+        Ok(Ok(value)) => Response {
+            id,
+            result: Some(value),
+            error: None,
+        },
+        Ok(Err(e)) => Response::new_err(id, ErrorCode::InternalError as i32, e.to_string()),
+        Err(e) => Response::new_err(id, ErrorCode::InvalidParams as i32, e.to_string()),
+    }
+}
+
+// The document a notification changes and its new text, or None for its text when it was
+// closed. Notifications that do not affect documents return None.
+fn handle_notification(notification: Notification) -> Result<Option<(Uri, Option<String>)>> {
+    Ok(match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params: DidOpenTextDocumentParams = serde_json::from_value(notification.params)?;
+            Some((params.text_document.uri, Some(params.text_document.text)))
+        }
+        DidChangeTextDocument::METHOD => {
+            let params: DidChangeTextDocumentParams = serde_json::from_value(notification.params)?;
+            // With full sync the last change holds the whole document
+            let text = params.content_changes.into_iter().last().map(|c| c.text);
+            text.map(|text| (params.text_document.uri, Some(text)))
+        }
+        DidCloseTextDocument::METHOD => {
+            let params: DidCloseTextDocumentParams = serde_json::from_value(notification.params)?;
+            Some((params.text_document.uri, None))
+        }
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{str::FromStr, thread};
+
+    use lsp_types::{
+        HoverContents, InitializeParams, TextDocumentIdentifier, TextDocumentItem,
+        TextDocumentPositionParams,
+        notification::{Exit, Initialized},
+        request::{Initialize, Shutdown},
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    fn labels(items: &[CompletionItem]) -> Vec<&str> {
+        items.iter().map(|item| item.label.as_str()).collect()
+    }
+
+    #[test]
+    fn test_diagnostics_cover_parse_and_translation_errors() {
+        let text = "push constant 1\n  push nowhere 2 // bad\n\npop constant 3\npush temp 1\n";
+        let analysis = Analysis::new(text, "Main");
+
+        let found: Vec<(Range, &str)> = analysis
+            .diagnostics
+            .iter()
+            .map(|diagnostic| (diagnostic.range, diagnostic.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (
+                    Range::new(Position::new(1, 2), Position::new(1, 16)),
+                    "Unknown segment: nowhere"
+                ),
+                (
+                    Range::new(Position::new(3, 0), Position::new(3, 14)),
+                    "Cannot pop constant"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hover_shows_generated_assembly() {
+        let analysis = Analysis::new("eq\n// comment\neq\n", "Main");
+
+        let Some(Hover {
+            contents: HoverContents::Markup(markup),
+            range,
+        }) = analysis.hover(Position::new(2, 1))
+        else {
+            panic!("expected a hover");
+        };
+        assert!(
+            markup.value.starts_with("```asm\n// eq\n"),
+            "{}",
+            markup.value
+        );
+        // Labels are numbered as in the translated file
+        assert!(
+            markup.value.contains("(Main.IsEqual.2)"),
+            "{}",
+            markup.value
+        );
+        assert_eq!(
+            range,
+            Some(Range::new(Position::new(2, 0), Position::new(2, 2)))
+        );
+
+        assert!(analysis.hover(Position::new(1, 0)).is_none());
+    }
+
+    #[test]
+    fn test_completions_depend_on_position() {
+        let text = "\npu\npush \npop l\npush local \n// pu";
+        assert_eq!(labels(&completions(text, Position::new(0, 0))).len(), 11);
+        assert!(labels(&completions(text, Position::new(1, 2))).contains(&"push"));
+
+        let segments = completions(text, Position::new(2, 5));
+        assert_eq!(labels(&segments).len(), 8);
+        assert!(labels(&completions(text, Position::new(3, 5))).contains(&"local"));
+        assert!(!labels(&completions(text, Position::new(3, 5))).contains(&"constant"));
+
+        assert!(completions(text, Position::new(4, 11)).is_empty());
+        assert!(completions(text, Position::new(5, 5)).is_empty());
+    }
+
+    #[test]
+    fn test_file_name_from_uri() {
+        let uri = Uri::from_str("file:///home/me/My%20Game/Main.vm").unwrap();
+        assert_eq!(file_name(&uri), "Main");
+        let uri = Uri::from_str("untitled:Untitled-1").unwrap();
+        assert_eq!(file_name(&uri), "Untitled-1");
+    }
+
+    #[test]
+    fn test_server_session() {
+        let (server, client) = Connection::memory();
+        let handle = thread::spawn(move || serve(server));
+        let uri = Uri::from_str("file:///project/Main.vm").unwrap();
+        let request = |id: i32, method: &str, params: serde_json::Value| {
+            client
+                .sender
+                .send(Message::Request(Request::new(
+                    id.into(),
+                    method.to_string(),
+                    params,
+                )))
+                .unwrap();
+            match client.receiver.recv().unwrap() {
+                Message::Response(response) => response,
+                other => panic!("expected a response, got {:?}", other),
+            }
+        };
+        let notify = |method: &str, params: serde_json::Value| {
+            client
+                .sender
+                .send(Message::Notification(Notification::new(
+                    method.to_string(),
+                    params,
+                )))
+                .unwrap();
+        };
+
+        let response = request(
+            1,
+            Initialize::METHOD,
+            serde_json::to_value(InitializeParams::default()).unwrap(),
+        );
+        assert_eq!(
+            response.result.unwrap()["capabilities"]["hoverProvider"],
+            true
+        );
+        notify(Initialized::METHOD, json!({}));
+
+        // Ignored without a reply, and without stopping the server
+        notify(DidOpenTextDocument::METHOD, json!({ "textDocument": 42 }));
+        notify(DidCloseTextDocument::METHOD, json!({}));
+
+        notify(
+            DidOpenTextDocument::METHOD,
+            json!({
+                "textDocument": TextDocumentItem::new(
+                    uri.clone(),
+                    "vm".to_string(),
+                    1,
+                    "push static 3\npop pointer 2\n".to_string(),
+                )
+            }),
+        );
+        let Message::Notification(published) = client.receiver.recv().unwrap() else {
+            panic!("expected diagnostics");
+        };
+        let published: PublishDiagnosticsParams = serde_json::from_value(published.params).unwrap();
+        assert_eq!(published.diagnostics.len(), 1);
+        assert_eq!(published.diagnostics[0].range.start.line, 1);
+
+        let response = request(
+            2,
+            HoverRequest::METHOD,
+            serde_json::to_value(TextDocumentPositionParams::new(
+                TextDocumentIdentifier::new(uri),
+                Position::new(0, 3),
+            ))
+            .unwrap(),
+        );
+        let hover = response.result.unwrap()["contents"]["value"].to_string();
+        assert!(hover.contains("@Main.3"), "{}", hover);
+
+        let response = request(3, "textDocument/definition", json!({}));
+        assert_eq!(
+            response.error.unwrap().code,
+            ErrorCode::MethodNotFound as i32
+        );
+
+        request(4, Shutdown::METHOD, serde_json::Value::Null);
+        notify(Exit::METHOD, serde_json::Value::Null);
+        handle.join().unwrap().unwrap();
+    }
+}
@@ -1,90 +1,645 @@
 use std::{
-    env::args,
-    ffi::OsStr,
     fs::{self, File},
-    io::BufWriter,
-    path::Path,
-    process::exit,
+    io::{self, BufWriter, Read},
+    path::{Path, PathBuf},
+    process::ExitCode,
 };
 
-use anyhow::{Result, anyhow};
+use anyhow::{Context, Result, anyhow};
+use clap::Parser;
 use std::io::Write;
 
-use crate::{
+use vm_translator::{
+    ast::Ast,
+    backend::{Backend, default_backend, find_backend, hack::HackBackend},
+    cache::{FragmentCache, Lookup},
+    cfg::ControlFlowGraph,
+    cli::{
+        Cli, CliCommand, DecompileArgs, Emit, FmtArgs, Input, InputArgs, LintArgs, Output, RunArgs,
+        TranslateArgs, Verbosity,
+    },
+    config::ProjectConfig,
     converter::{Converter, HackConverter},
-    lexer::Lexer,
+    decompiler::{Decompiled, decompile as decompile_assembly},
+    formatter::format_source,
+    interpreter::Interpreter,
+    labels::{LabelScheme, check_unique_labels},
+    lint::lint as lint_sources,
+    memory_layout::MemoryLayout,
+    parallel::map_ordered,
+    program::{
+        Source, convert_source, default_output_path, input_files, open_sources, translate_files,
+    },
+    stack_depth::StackDepth,
+    stats::ProgramStats,
 };
 
-mod command;
-mod converter;
-mod lexer;
+// Exit codes follow the BSD sysexits convention. Usage errors exit with 2 from clap.
+const EXIT_DATA_ERR: u8 = 65; // the VM source could not be parsed or translated
+const EXIT_NO_INPUT: u8 = 66; // the input file does not exist
+const EXIT_IO_ERR: u8 = 74; // reading the input or writing the output failed
+const EXIT_UNFORMATTED: u8 = 1; // fmt --check found files that are not formatted
+const EXIT_LINT_WARNINGS: u8 = 1; // lint found suspicious code
 
-fn main() {
-    println!("Starting translation...");
-    let mut args = args();
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let verbosity = cli.verbosity();
 
-    if args.len() < 2 {
-        println!("Usage: vtranslate <input_file_path>")
+    let input = match &cli.command {
+        CliCommand::Translate(args) => args.input.input(),
+        CliCommand::Run(args) => args.input.input(),
+        CliCommand::Fmt(args) => args.input.input(),
+        CliCommand::Check(args) | CliCommand::Stats(args) => args.input(),
+        CliCommand::Decompile(args) => args.input(),
+        CliCommand::Lint(args) => args.input.input(),
+    };
+    if let Input::File(path) = &input
+        && !path.exists()
+    {
+        eprintln!("Input file {} not found", path.display());
+        return ExitCode::from(EXIT_NO_INPUT);
     }
 
-    let input_file_path = args.nth(1).expect("input file path not found");
-    println!("Input file path: {}", &input_file_path);
+    let config = match &cli.command {
+        CliCommand::Translate(_) | CliCommand::Lint(_) => {
+            match project_config(cli.config.as_deref(), &input, verbosity) {
+                Ok(config) => config,
+                Err(e) => {
+                    eprintln!("Error: {:#}", e);
+                    return exit_code_for(&e);
+                }
+            }
+        }
+        _ => ProjectConfig::default(),
+    };
+
+    let result = match cli.command {
+        CliCommand::Translate(args) => translate(args, config, verbosity),
+        CliCommand::Run(args) => run(args, verbosity),
+        CliCommand::Check(args) => check(args, verbosity),
+        CliCommand::Stats(args) => stats(args, verbosity),
+        CliCommand::Fmt(args) => fmt(args, verbosity),
+        CliCommand::Decompile(args) => decompile(args, verbosity),
+        CliCommand::Lint(args) => lint(args, config, verbosity),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            exit_code_for(&e)
+        }
+    }
+}
+
+// The configuration given by --config, or else the one found for the input, if any
+fn project_config(
+    path: Option<&Path>,
+    input: &Input,
+    verbosity: Verbosity,
+) -> Result<ProjectConfig> {
+    let path = match (path, input) {
+        (Some(path), _) => Some(path.to_path_buf()),
+        (None, Input::File(input) | Input::Directory(input)) => ProjectConfig::discover(input),
+        (None, Input::Stdin) => ProjectConfig::discover(Path::new(".")),
+    };
+    let Some(path) = path else {
+        return Ok(ProjectConfig::default());
+    };
+    verbosity.detail(format!("Configuration: {}", path.display()));
+    ProjectConfig::load(&path)
+}
+
+fn exit_code_for(err: &anyhow::Error) -> ExitCode {
+    let is_io = err.chain().any(|cause| cause.is::<io::Error>());
+    ExitCode::from(if is_io { EXIT_IO_ERR } else { EXIT_DATA_ERR })
+}
+
+fn open_output(output: &Output) -> Result<Box<dyn Write>> {
+    match output {
+        Output::Stdout => Ok(Box::new(io::stdout().lock())),
+        Output::File(path) => {
+            let file = File::create(path)
+                .with_context(|| format!("failed to create {}", path.display()))?;
+            Ok(Box::new(file))
+        }
+    }
+}
+
+fn translate(args: TranslateArgs, config: ProjectConfig, verbosity: Verbosity) -> Result<ExitCode> {
+    let mut hack = None;
+    let backend = translate_backend(&args, &config, &mut hack)?;
+    let input = args.input.input();
+    let output = match (args.output.as_deref(), config.output, &input) {
+        (_, _, _) if args.stdout => Output::Stdout,
+        (Some("-"), _, _) => Output::Stdout,
+        (Some(path), _, _) => Output::File(PathBuf::from(path)),
+        (None, Some(path), _) => Output::File(path),
+        (None, None, Input::File(path) | Input::Directory(path)) => {
+            let extension = match args.emit {
+                Emit::Ast => "json",
+                Emit::Cfg => "dot",
+                _ => backend.extension(),
+            };
+            Output::File(default_output_path(path, extension))
+        }
+        (None, None, Input::Stdin) => Output::Stdout,
+    };
+
+    if args.watch {
+        let (Input::File(input) | Input::Directory(input)) = input else {
+            return Err(anyhow!("--watch needs a file or directory, not stdin"));
+        };
+        let Output::File(output) = output else {
+            return Err(anyhow!("--watch needs an output file, not stdout"));
+        };
+        vm_translator::watch::watch(input, output, backend, verbosity)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    verbosity.status("Starting translation...");
+    verbosity.detail(format!("Target: {}", backend.name()));
+    // Everything is translated before the output is opened, so an error cannot leave a
+    // truncated or partly written file behind
+    let mut writer = Vec::new();
+    if args.emit == Emit::Asm {
+        writer.write_all(backend.prologue().as_bytes())?;
+    }
+
+    if args.incremental {
+        let (Input::File(input) | Input::Directory(input)) = &input else {
+            return Err(anyhow!(
+                "--incremental needs a file or directory, not stdin"
+            ));
+        };
+        let cache_dir = args
+            .cache_dir
+            .unwrap_or_else(|| FragmentCache::default_dir(input));
+        let cache = FragmentCache::new(cache_dir, backend);
+        translate_incremental(input, &cache, backend, args.jobs, &mut writer, verbosity)?;
+    } else if args.emit == Emit::Ast {
+        let ast = Ast::parse(open_sources(&input)?)?;
+        serde_json::to_writer_pretty(&mut writer, &ast)?;
+        writeln!(writer)?;
+    } else if args.emit == Emit::Cfg {
+        for cfg in ControlFlowGraph::for_sources(open_sources(&input)?)? {
+            writer.write_all(cfg.to_dot().as_bytes())?;
+        }
+    } else if let (Input::File(input) | Input::Directory(input), Emit::Asm) = (&input, args.emit) {
+        let files = input_files(input)?;
+        verbosity.detail(format!(
+            "Translating {} file(s) with {} job(s)",
+            files.len(),
+            args.jobs
+        ));
+        let fragments = files
+            .iter()
+            .zip(translate_files(&files, backend, args.jobs))
+            .map(|(file, fragment)| fragment.with_context(|| file.display().to_string()))
+            .collect::<Result<Vec<_>>>()?;
+        check_labels(&files, &fragments, backend)?;
+        for fragment in fragments {
+            writer.write_all(fragment.as_bytes())?;
+        }
+    } else {
+        let sources = open_sources(&input)?;
+        translate_sources(sources, backend, args.emit, &mut writer, verbosity)?;
+    }
+    if args.emit == Emit::Asm {
+        writer.write_all(backend.epilogue().as_bytes())?;
+    }
+    let mut file = open_output(&output)?;
+    file.write_all(&writer)?;
+    file.flush()?;
 
-    match fs::exists(&input_file_path) {
-        Ok(exists) => {
-            if exists {
-                println!("Input file found")
-            } else {
-                println!("Input file {} not found", &input_file_path);
-                exit(1)
+    if let Output::File(path) = &output {
+        verbosity.detail(format!("Wrote {}", path.display()));
+    }
+    verbosity.status("Translation completed...");
+    Ok(ExitCode::SUCCESS)
+}
+
+// The backend for --target, or the configuration's target. The hack target is set up with
+// the memory layout from --memory-layout and --layout and the --label-scheme, or else the
+// configuration's. A Hack backend with other settings than the built-in one is kept in `hack`.
+fn translate_backend<'a>(
+    args: &TranslateArgs,
+    config: &ProjectConfig,
+    hack: &'a mut Option<HackBackend>,
+) -> Result<&'a dyn Backend> {
+    let target = args
+        .target
+        .as_deref()
+        .or(config.target.as_deref())
+        .unwrap_or(default_backend().name());
+    let backend = find_backend(target)?;
+    if backend.name() != "hack" {
+        // The configuration's Hack settings are for when the project is translated for Hack
+        if args.has_hack_options() {
+            return Err(anyhow!(
+                "--memory-layout, --layout and --label-scheme only apply to the hack target, \
+                 not {}",
+                backend.name()
+            ));
+        }
+        return Ok(backend);
+    }
+
+    let layout = match &args.memory_layout {
+        Some(path) => MemoryLayout::load(Some(path), &args.layout_overrides)?,
+        None => config.memory_layout(&args.layout_overrides)?,
+    };
+    let labels = args
+        .label_scheme
+        .clone()
+        .or(config.label_scheme.clone())
+        .unwrap_or_default();
+    if layout == MemoryLayout::STANDARD && labels == LabelScheme::DEFAULT {
+        return Ok(backend);
+    }
+    Ok(hack.insert(HackBackend::new(layout, labels)))
+}
+
+fn translate_sources(
+    sources: Vec<Source>,
+    backend: &dyn Backend,
+    emit: Emit,
+    writer: &mut impl Write,
+    verbosity: Verbosity,
+) -> Result<()> {
+    for source in sources {
+        verbosity.detail(format!("Translating {}", source.display_name));
+        let display_name = source.display_name.clone();
+        let end = convert_source(source, backend, |line, command, converted| {
+            match emit {
+                Emit::Asm => writeln!(writer, "{}", converted)?,
+                Emit::Commands => writeln!(writer, "{}:{}: {}", display_name, line, command)?,
+                Emit::Ast | Emit::Cfg => unreachable!("written without translating"),
             }
+            Ok(())
+        })
+        .with_context(|| display_name.clone())?;
+        if emit == Emit::Asm && !end.is_empty() {
+            writeln!(writer, "{}", end)?;
         }
-        Err(_) => {
-            println!("Error checking file: {}", &input_file_path);
-            exit(1)
+    }
+    Ok(())
+}
+
+fn translate_incremental(
+    input: &Path,
+    cache: &FragmentCache,
+    backend: &dyn Backend,
+    jobs: usize,
+    writer: &mut impl Write,
+    verbosity: Verbosity,
+) -> Result<()> {
+    let files = input_files(input)?;
+    let results = map_ordered(&files, jobs, |file| cache.translate(file));
+    let mut fragments = Vec::new();
+    let mut reused = 0;
+
+    for (file, result) in files.iter().zip(results) {
+        let (fragment, lookup) = result.with_context(|| file.display().to_string())?;
+        match lookup {
+            Lookup::Hit => {
+                verbosity.detail(format!("Reused {}", file.display()));
+                reused += 1;
+            }
+            Lookup::Miss => verbosity.detail(format!("Translating {}", file.display())),
         }
+        fragments.push(fragment);
+    }
+    check_labels(&files, &fragments, backend)?;
+    for fragment in fragments {
+        writer.write_all(fragment.as_bytes())?;
     }
 
-    let input_file_path = Path::new(input_file_path.as_str());
+    verbosity.status(format!(
+        "Reused {} of {} file(s) from the cache",
+        reused,
+        files.len()
+    ));
+    Ok(())
+}
+
+// Fails if the translated files define the same label, before their fragments are combined
+fn check_labels(files: &[PathBuf], fragments: &[String], backend: &dyn Backend) -> Result<()> {
+    let names: Vec<String> = files
+        .iter()
+        .map(|file| file.display().to_string())
+        .collect();
+    check_unique_labels(
+        names
+            .iter()
+            .zip(fragments)
+            .map(|(name, fragment)| (name.as_str(), backend.defined_labels(fragment))),
+    )
+}
+
+fn run(args: RunArgs, verbosity: Verbosity) -> Result<ExitCode> {
+    let sources = open_sources(&args.input.input())?;
+    let mut interpreter = Interpreter::new();
+    for (address, value) in &args.assignments {
+        interpreter.set(*address, *value)?;
+        verbosity.detail(format!("RAM[{}] = {}", address, value));
+    }
 
-    match translate(input_file_path) {
-        Ok(_) => {
-            println!("Translation completed...")
+    let mut executed = 0;
+    for source in sources {
+        for result in source.lexer {
+            let lexed_res = result.with_context(|| source.display_name.clone())?;
+            let Some(command) = lexed_res.command else {
+                continue;
+            };
+            interpreter
+                .execute(&source.file_name, &command)
+                .map_err(|e| anyhow!("line {}: {}", lexed_res.line, e))
+                .with_context(|| source.display_name.clone())?;
+            executed += 1;
         }
-        Err(e) => {
-            println!("Error during translation: {}", e);
+    }
+    verbosity.detail(format!("Executed {} commands", executed));
+
+    let ram = interpreter.ram();
+    println!(
+        "SP={} LCL={} ARG={} THIS={} THAT={}",
+        ram[0], ram[1], ram[2], ram[3], ram[4]
+    );
+    let stack: Vec<String> = interpreter.stack().iter().map(i16::to_string).collect();
+    println!("stack: [{}]", stack.join(", "));
+    Ok(ExitCode::SUCCESS)
+}
+
+fn check(args: InputArgs, verbosity: Verbosity) -> Result<ExitCode> {
+    let mut errors = 0;
+
+    // Files share the stack, in the order they run
+    let mut stack = StackDepth::new();
+
+    for source in open_sources(&args.input())? {
+        let mut converter = HackConverter::new(source.file_name);
+
+        // Unlike translation, keep going after an error so every problem is reported at once
+        for result in source.lexer {
+            let outcome = result.and_then(|lexed_res| match lexed_res.command {
+                Some(command) => converter
+                    .convert(command.clone())
+                    .and_then(|_| stack.apply(&command))
+                    .map_err(|e| anyhow!("line {}: {}", lexed_res.line, e)),
+                None => Ok(()),
+            });
+
+            if let Err(e) = outcome {
+                if e.chain().any(|cause| cause.is::<io::Error>()) {
+                    return Err(e.context(source.display_name));
+                }
+                eprintln!("{}: {:#}", source.display_name, e);
+                errors += 1;
+            }
         }
     }
+
+    if errors > 0 {
+        verbosity.status(format!("{} error(s) found", errors));
+        return Ok(ExitCode::from(EXIT_DATA_ERR));
+    }
+    verbosity.status("No errors found");
+    Ok(ExitCode::SUCCESS)
 }
 
-fn translate(input_path: &Path) -> Result<()> {
-    let input_file_stem = input_path.file_stem().unwrap_or(OsStr::new("output"));
-    let input_file_name = input_file_stem
-        .to_os_string()
-        .into_string()
-        .map_err(|e| anyhow!("Failed to convert OsString to String: {:?}", e))?;
+fn stats(args: InputArgs, verbosity: Verbosity) -> Result<ExitCode> {
+    let mut stats = ProgramStats::default();
 
-    let input_dir = input_path.parent().unwrap_or(Path::new("."));
-    let output_path = input_dir.join(Path::new(input_file_stem).with_extension("asm"));
+    for source in open_sources(&args.input())? {
+        let mut converter = HackConverter::new(source.file_name);
 
-    let lexer = Lexer::new(input_path)?;
-    let output_file = File::create(output_path)?;
-    let mut writer = BufWriter::new(output_file);
-    let mut converter = HackConverter::new(input_file_name);
+        for result in source.lexer {
+            let lexed_res = result.with_context(|| source.display_name.clone())?;
+            stats.record_line();
+            let Some(command) = lexed_res.command else {
+                continue;
+            };
+            let converted = converter
+                .convert(command.clone())
+                .map_err(|e| anyhow!("line {}: {}", lexed_res.line, e))
+                .with_context(|| source.display_name.clone())?;
+            stats.record(&command, &converted);
+        }
+    }
 
-    for result in lexer {
-        let lexed_res = result?;
-        if lexed_res.skippable {
-            continue;
+    verbosity.detail(format!("Statistics for {}", args.input));
+    print!("{}", stats);
+    Ok(ExitCode::SUCCESS)
+}
+
+fn fmt(args: FmtArgs, verbosity: Verbosity) -> Result<ExitCode> {
+    let paths = match args.input.input() {
+        Input::Stdin => {
+            let mut content = Vec::new();
+            io::stdin().read_to_end(&mut content)?;
+            let formatted = format_source(Source::from_bytes(Path::new("-"), content.clone())?)?;
+            if args.check {
+                return Ok(if formatted.as_bytes() == content {
+                    ExitCode::SUCCESS
+                } else {
+                    ExitCode::from(EXIT_UNFORMATTED)
+                });
+            }
+            io::stdout().write_all(formatted.as_bytes())?;
+            return Ok(ExitCode::SUCCESS);
+        }
+        Input::File(path) | Input::Directory(path) => input_files(&path)?,
+    };
+
+    // Every file is formatted before any is rewritten, so an error leaves all of them as
+    // they were
+    let mut changes = Vec::new();
+    for path in &paths {
+        let content =
+            fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let formatted = format_source(Source::from_bytes(path, content.clone())?)
+            .with_context(|| path.display().to_string())?;
+        if formatted.as_bytes() == content {
+            verbosity.detail(format!("{} is already formatted", path.display()));
+        } else {
+            changes.push((path, formatted));
+        }
+    }
+
+    let unformatted = changes.len();
+    for (path, formatted) in changes {
+        if args.check {
+            println!("{}", path.display());
+        } else {
+            fs::write(path, formatted)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            verbosity.detail(format!("Formatted {}", path.display()));
         }
+    }
 
-        let command = lexed_res
-            .command
-            .ok_or_else(|| anyhow!("Command not found"))?;
-        let converted = converter.convert(command)?;
-        writeln!(writer, "{}", converted)?
+    if args.check {
+        if unformatted > 0 {
+            verbosity.status(format!(
+                "{} of {} file(s) not formatted",
+                unformatted,
+                paths.len()
+            ));
+            return Ok(ExitCode::from(EXIT_UNFORMATTED));
+        }
+        verbosity.status(format!("All {} file(s) formatted", paths.len()));
+    } else {
+        verbosity.status(format!(
+            "Formatted {} of {} file(s)",
+            unformatted,
+            paths.len()
+        ));
     }
+    Ok(ExitCode::SUCCESS)
+}
 
+fn decompile(args: DecompileArgs, verbosity: Verbosity) -> Result<ExitCode> {
+    let (assembly, display_name) = match args.input() {
+        Input::File(path) => (
+            fs::read_to_string(&path)
+                .with_context(|| format!("failed to read {}", path.display()))?,
+            path.display().to_string(),
+        ),
+        _ => {
+            let mut assembly = String::new();
+            io::stdin().read_to_string(&mut assembly)?;
+            (assembly, "-".to_string())
+        }
+    };
+
+    let output = match args.output {
+        Some(path) => Output::File(path),
+        None => Output::Stdout,
+    };
+    let mut writer = BufWriter::new(open_output(&output)?);
+    let mut commands = 0;
+    let mut unrecognized = 0;
+    for entry in decompile_assembly(&assembly) {
+        match entry {
+            Decompiled::Command { command, .. } => {
+                writeln!(writer, "{}", command)?;
+                commands += 1;
+            }
+            Decompiled::Unrecognized {
+                lines,
+                instructions,
+            } => {
+                let lines = format!("lines {}-{}", lines.start(), lines.end());
+                eprintln!(
+                    "{}: {}: {} unrecognized instruction(s)",
+                    display_name,
+                    lines,
+                    instructions.len()
+                );
+                writeln!(writer, "// unrecognized: {}", lines)?;
+                unrecognized += 1;
+            }
+        }
+    }
     writer.flush()?;
-    Ok(())
+
+    verbosity.detail(format!("Recovered {} command(s)", commands));
+    if unrecognized > 0 {
+        verbosity.status(format!(
+            "{} region(s) could not be decompiled",
+            unrecognized
+        ));
+        return Ok(ExitCode::from(EXIT_DATA_ERR));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn lint(args: LintArgs, config: ProjectConfig, verbosity: Verbosity) -> Result<ExitCode> {
+    let selection = args.selection();
+    let rules = if selection.is_empty() {
+        config.lint.rules()
+    } else {
+        selection.rules()
+    };
+    verbosity.detail(format!(
+        "Rules: {}",
+        rules
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(", ")
+    ));
+
+    let warnings = lint_sources(open_sources(&args.input.input())?, &rules)?;
+    for warning in &warnings {
+        eprintln!("{}", warning);
+    }
+
+    if !warnings.is_empty() {
+        verbosity.status(format!("{} warning(s) found", warnings.len()));
+        return Ok(ExitCode::from(EXIT_LINT_WARNINGS));
+    }
+    verbosity.status("No warnings found");
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fmt_rejects_extra_tokens_without_writing() {
+        let dir = std::env::temp_dir().join(format!("vm_translator_{}_fmt", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Main.vm");
+        let content = "push  constant 1\nadd extra\n";
+        fs::write(&path, content).unwrap();
+
+        let args = FmtArgs {
+            input: InputArgs {
+                input: path.display().to_string(),
+            },
+            check: false,
+        };
+        let err = fmt(args, Verbosity::Quiet).unwrap_err();
+        let unchanged = fs::read_to_string(&path).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(
+            format!("{:#}", err).contains("line 2: Unexpected token: extra"),
+            "{:#}",
+            err
+        );
+        assert_eq!(unchanged, content);
+    }
+
+    #[test]
+    fn test_failed_translation_keeps_previous_output() {
+        let dir = std::env::temp_dir().join(format!(
+            "vm_translator_{}_translate_error",
+            std::process::id()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("Main.vm");
+        let output = dir.join("Main.asm");
+        fs::write(&input, "push constant 1\nbogus\n").unwrap();
+        fs::write(&output, "previous output\n").unwrap();
+
+        let cli = Cli::parse_from([
+            "vm_translator",
+            "translate",
+            input.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+        ]);
+        let CliCommand::Translate(args) = cli.command else {
+            unreachable!("parsed a translate command");
+        };
+        let result = translate(args, ProjectConfig::default(), Verbosity::Quiet);
+        let kept = fs::read_to_string(&output).unwrap();
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(result.is_err());
+        assert_eq!(kept, "previous output\n");
+    }
 }
@@ -0,0 +1,219 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+
+// One past the last RAM address that holds data, where the screen memory map starts
+const RAM_END: u32 = 16384;
+// Where the assembler starts allocating variables, such as static variables
+const VARIABLES_BASE: u16 = 16;
+
+/// Where the Hack code generated by [`HackConverter`](crate::converter::HackConverter)
+/// expects things to be in RAM. The standard layout is the one from Nand to Tetris; the
+/// others are for modified Hack machines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MemoryLayout {
+    /// Address of the stack pointer
+    pub sp: u16,
+    /// Addresses of the segment base pointers
+    pub lcl: u16,
+    pub arg: u16,
+    pub this: u16,
+    pub that: u16,
+    /// First address and number of cells of the `temp` segment
+    pub temp_base: u16,
+    pub temp_size: u16,
+    /// Two registers `pop` can use to hold a value and an address. When unset, `pop`
+    /// uses variables allocated by the assembler instead.
+    pub scratch: Option<[u16; 2]>,
+    /// Where the stack starts. Code to set `SP` is only generated when this is not 256,
+    /// since the standard tools set it themselves. RAM from 16 up to here is left to the
+    /// variables the assembler allocates.
+    pub stack_base: u16,
+}
+
+impl MemoryLayout {
+    pub const STANDARD: MemoryLayout = MemoryLayout {
+        sp: 0,
+        lcl: 1,
+        arg: 2,
+        this: 3,
+        that: 4,
+        temp_base: 5,
+        temp_size: 8,
+        scratch: None,
+        stack_base: 256,
+    };
+
+    /// Reads a layout from a TOML file, then applies `KEY=VALUE` overrides such as
+    /// `temp_size=4`. Settings that are given in neither keep their standard value.
+    pub fn load(path: Option<&Path>, overrides: &[String]) -> Result<Self> {
+        let table = match path {
+            Some(path) => {
+                let text = fs::read_to_string(path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                toml::from_str(&text).with_context(|| path.display().to_string())?
+            }
+            None => toml::Table::new(),
+        };
+        Self::from_table(table, overrides)
+    }
+
+    /// Like [`load`](Self::load), for settings that have already been read, such as the
+    /// `[memory_layout]` table of a project configuration file.
+    pub fn from_table(mut table: toml::Table, overrides: &[String]) -> Result<Self> {
+        for assignment in overrides {
+            let (key, value) = assignment
+                .split_once('=')
+                .ok_or_else(|| anyhow!("expected KEY=VALUE but got {}", assignment))?;
+            let value: toml::Value = toml::from_str::<toml::Table>(&format!("v = {}", value))
+                .ok()
+                .and_then(|mut parsed| parsed.remove("v"))
+                .ok_or_else(|| anyhow!("invalid value for {}: {}", key, value))?;
+            table.insert(key.trim().to_string(), value);
+        }
+
+        let layout: Self = table.try_into().context("invalid memory layout")?;
+        layout.validate()?;
+        Ok(layout)
+    }
+
+    /// Checks that no two regions overlap and that everything fits in RAM.
+    pub fn validate(&self) -> Result<()> {
+        if self.temp_size == 0 {
+            return Err(anyhow!("temp_size must be at least 1"));
+        }
+        if self.stack_base <= VARIABLES_BASE {
+            return Err(anyhow!(
+                "the stack at {} must start after address {}, where the assembler \
+                 allocates variables",
+                self.stack_base,
+                VARIABLES_BASE
+            ));
+        }
+        let mut regions = vec![
+            (
+                "variables",
+                VARIABLES_BASE as u32,
+                (self.stack_base - VARIABLES_BASE) as u32,
+            ),
+            ("sp", self.sp as u32, 1),
+            ("lcl", self.lcl as u32, 1),
+            ("arg", self.arg as u32, 1),
+            ("this", self.this as u32, 1),
+            ("that", self.that as u32, 1),
+            ("temp", self.temp_base as u32, self.temp_size as u32),
+        ];
+        if let Some([first, second]) = self.scratch {
+            regions.push(("scratch", first as u32, 1));
+            regions.push(("scratch", second as u32, 1));
+        }
+        // The stack grows up from its base, and may use the rest of RAM
+        regions.push((
+            "stack",
+            self.stack_base as u32,
+            RAM_END.saturating_sub(self.stack_base as u32),
+        ));
+
+        for (i, &(name, start, size)) in regions.iter().enumerate() {
+            if start + size > RAM_END {
+                return Err(anyhow!("{} does not fit in RAM", name));
+            }
+            for &(other, other_start, other_size) in &regions[..i] {
+                if start < other_start + other_size && other_start < start + size {
+                    return Err(anyhow!("{} overlaps {}", name, other));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The operand of an A-instruction that addresses a pointer register: the predefined
+    /// symbol when the register is at its standard address, otherwise the address itself.
+    pub fn register(&self, name: &str) -> String {
+        let (address, standard) = match name {
+            "SP" => (self.sp, Self::STANDARD.sp),
+            "LCL" => (self.lcl, Self::STANDARD.lcl),
+            "ARG" => (self.arg, Self::STANDARD.arg),
+            "THIS" => (self.this, Self::STANDARD.this),
+            "THAT" => (self.that, Self::STANDARD.that),
+            _ => unreachable!("{} is not a pointer register", name),
+        };
+        if address == standard {
+            name.to_string()
+        } else {
+            address.to_string()
+        }
+    }
+}
+
+impl Default for MemoryLayout {
+    fn default() -> Self {
+        Self::STANDARD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_load_merges_file_and_overrides() {
+        let dir = TempDir::new("memory_layout");
+        let path = dir.0.join("layout.toml");
+        fs::write(&path, "temp_base = 6\ntemp_size = 4\nscratch = [13, 14]\n").unwrap();
+
+        let layout = MemoryLayout::load(Some(&path), &["temp_size=6".to_string()]).unwrap();
+        assert_eq!(
+            layout,
+            MemoryLayout {
+                temp_base: 6,
+                temp_size: 6,
+                scratch: Some([13, 14]),
+                ..MemoryLayout::STANDARD
+            }
+        );
+        assert_eq!(
+            MemoryLayout::load(None, &[]).unwrap(),
+            MemoryLayout::STANDARD
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_bad_settings() {
+        let cases = [
+            ("temp_bse=20", "invalid memory layout"),
+            ("temp_base", "expected KEY=VALUE"),
+            ("temp_base=-1", "invalid memory layout"),
+            ("temp_base=3", "temp overlaps this"),
+            ("scratch=[13, 6]", "scratch overlaps temp"),
+            ("temp_base=20", "temp overlaps variables"),
+            ("sp=255", "sp overlaps variables"),
+            ("stack_base=16", "must start after address 16"),
+            ("stack_base=20000", "does not fit in RAM"),
+            ("temp_size=0", "temp_size must be at least 1"),
+            ("heap_base=2048", "invalid memory layout"),
+        ];
+        for (assignment, expected) in cases {
+            let err = MemoryLayout::load(None, &[assignment.to_string()]).unwrap_err();
+            assert!(
+                format!("{:#}", err).contains(expected),
+                "{}: {:#}",
+                assignment,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_registers_use_symbols_only_at_standard_addresses() {
+        let layout = MemoryLayout {
+            sp: 15,
+            ..MemoryLayout::STANDARD
+        };
+        assert_eq!(layout.register("SP"), "15");
+        assert_eq!(layout.register("LCL"), "LCL");
+    }
+}
@@ -0,0 +1,82 @@
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+/// The number of worker threads to use when none is requested explicitly.
+pub fn default_jobs() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+/// Runs `job` on every item using up to `jobs` threads and returns the results in the same
+/// order as `items`, regardless of which thread finished first.
+pub fn map_ordered<T, R, F>(items: &[T], jobs: usize, job: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let jobs = jobs.clamp(1, items.len().max(1));
+    if jobs == 1 {
+        return items.iter().map(job).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<R>>> = Mutex::new(items.iter().map(|_| None).collect());
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+                    let result = job(item);
+                    results.lock().expect("a worker panicked")[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .expect("a worker panicked")
+        .into_iter()
+        .map(|result| result.expect("every item is processed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn test_results_keep_input_order() {
+        let items: Vec<u64> = (0..32).collect();
+        // Earlier items take longer so they finish last
+        let results = map_ordered(&items, 4, |item| {
+            thread::sleep(Duration::from_millis(32 - item));
+            item * 10
+        });
+
+        assert_eq!(
+            results,
+            items.iter().map(|item| item * 10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_job_counts_are_clamped() {
+        let items = [1, 2, 3];
+        assert_eq!(map_ordered(&items, 0, |item| item + 1), vec![2, 3, 4]);
+        assert_eq!(map_ordered(&items, 64, |item| item + 1), vec![2, 3, 4]);
+        assert!(map_ordered(&[] as &[u8], 8, |item| *item).is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+use std::{
+    ffi::OsStr,
+    fs,
+    io::{self, BufRead},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+
+use crate::{backend::Backend, cli::Input, command::Command, lexer::Lexer, parallel::map_ordered};
+
+// Name used for static variables when the program is read from stdin
+const STDIN_FILE_NAME: &str = "stdin";
+
+/// A VM file to read, along with the name its static variables and labels are scoped under.
+pub struct Source {
+    pub lexer: Lexer<Box<dyn BufRead>>,
+    pub file_name: String,
+    // How the source is referred to in diagnostics
+    pub display_name: String,
+}
+
+impl Source {
+    pub fn stdin() -> Self {
+        Self {
+            lexer: Lexer::from_reader(Box::new(io::stdin().lock())),
+            file_name: STDIN_FILE_NAME.to_string(),
+            display_name: "-".to_string(),
+        }
+    }
+
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            lexer: Lexer::new(path)?,
+            file_name: file_name(path)?,
+            display_name: path.display().to_string(),
+        })
+    }
+
+    /// A source whose contents have already been read into memory.
+    pub fn from_bytes(path: &Path, content: Vec<u8>) -> Result<Self> {
+        Ok(Self {
+            lexer: Lexer::from_reader(Box::new(io::Cursor::new(content))),
+            file_name: file_name(path)?,
+            display_name: path.display().to_string(),
+        })
+    }
+}
+
+/// Opens every source making up the program: a single file, stdin, or each `.vm` file in
+/// a directory in name order.
+pub fn open_sources(input: &Input) -> Result<Vec<Source>> {
+    match input {
+        Input::Stdin => Ok(vec![Source::stdin()]),
+        Input::File(path) => Ok(vec![Source::open(path)?]),
+        Input::Directory(dir) => vm_files(dir)?
+            .iter()
+            .map(|path| Source::open(path))
+            .collect(),
+    }
+}
+
+/// Lists the files making up a file or directory input.
+pub fn input_files(input: &Path) -> Result<Vec<PathBuf>> {
+    if input.is_dir() {
+        vm_files(input)
+    } else {
+        Ok(vec![input.to_path_buf()])
+    }
+}
+
+/// Lists the `.vm` files directly inside `dir`, sorted so output order is stable.
+pub fn vm_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_file() && path.extension() == Some(OsStr::new("vm")) {
+            files.push(path);
+        }
+    }
+
+    if files.is_empty() {
+        return Err(anyhow!("No .vm files found in {}", dir.display()));
+    }
+    files.sort();
+    Ok(files)
+}
+
+pub fn file_name(path: &Path) -> Result<String> {
+    path.file_stem()
+        .unwrap_or(OsStr::new("output"))
+        .to_os_string()
+        .into_string()
+        .map_err(|e| anyhow!("Failed to convert OsString to String: {:?}", e))
+}
+
+/// A file's output goes next to it with the backend's extension. A directory's combined
+/// output goes inside it, named after the directory.
+pub fn default_output_path(input: &Path, extension: &str) -> PathBuf {
+    if input.is_dir() {
+        let name = input.file_name().unwrap_or(OsStr::new("output"));
+        return input.join(Path::new(name).with_extension(extension));
+    }
+
+    let input_file_stem = input.file_stem().unwrap_or(OsStr::new("output"));
+    let input_dir = input.parent().unwrap_or(Path::new("."));
+    input_dir.join(Path::new(input_file_stem).with_extension(extension))
+}
+
+/// Lexes and converts a single source, handing every command and its translation to
+/// `emit`. Stops at the first error. Returns the code the converter adds after the last
+/// command, see [`Converter::finish`].
+pub fn convert_source(
+    source: Source,
+    backend: &dyn Backend,
+    mut emit: impl FnMut(usize, &Command, String) -> Result<()>,
+) -> Result<String> {
+    let mut converter = backend.converter(&source.file_name);
+
+    for result in source.lexer {
+        let lexed_res = result?;
+        if lexed_res.skippable {
+            continue;
+        }
+
+        let command = lexed_res
+            .command
+            .ok_or_else(|| anyhow!("line {}: Command not found", lexed_res.line))?;
+        let converted = converter
+            .convert(command.clone())
+            .map_err(|e| anyhow!("line {}: {}", lexed_res.line, e))?;
+        emit(lexed_res.line, &command, converted)?;
+    }
+
+    Ok(converter.finish())
+}
+
+/// Translates one `.vm` file into a self-contained fragment of the backend's output.
+/// Fragments of different files can be concatenated in any order, then wrapped in the
+/// backend's prologue and epilogue.
+pub fn translate_file(path: &Path, backend: &dyn Backend) -> Result<String> {
+    translate_source(Source::open(path)?, backend)
+}
+
+/// Translates each file on up to `jobs` threads. Results are in the same order as `files`,
+/// and since every file has its own converter the output does not depend on scheduling.
+pub fn translate_files(
+    files: &[PathBuf],
+    backend: &dyn Backend,
+    jobs: usize,
+) -> Vec<Result<String>> {
+    map_ordered(files, jobs, |path| translate_file(path, backend))
+}
+
+/// Translates a source into a fragment of the backend's output, see [`translate_file`].
+pub fn translate_source(source: Source, backend: &dyn Backend) -> Result<String> {
+    let mut fragment = String::new();
+    let end = convert_source(source, backend, |_, _, converted| {
+        fragment.push_str(&converted);
+        fragment.push('\n');
+        Ok(())
+    })?;
+    if !end.is_empty() {
+        fragment.push_str(&end);
+        fragment.push('\n');
+    }
+    Ok(fragment)
+}
@@ -0,0 +1,101 @@
+use anyhow::{Result, anyhow};
+
+use crate::command::Command;
+
+/// Tracks how many values are on the VM stack as a program's commands run, assuming it starts
+/// empty. The supported commands never branch, so a program is a single block whose depth is
+/// known exactly at every command. Files of a program share one stack and run one after the
+/// other, so one tracker follows all of them in order.
+#[derive(Debug, Default)]
+pub struct StackDepth {
+    depth: usize,
+    max_depth: usize,
+}
+
+/// Values a command pops from the stack and pushes back onto it.
+pub fn stack_effect(command: &Command) -> (usize, usize) {
+    match command {
+        Command::Push { .. } => (0, 1),
+        Command::Pop { .. } => (1, 0),
+        Command::Neg | Command::Not => (1, 1),
+        Command::Add
+        | Command::Sub
+        | Command::Eq
+        | Command::Gt
+        | Command::Lt
+        | Command::And
+        | Command::Or => (2, 1),
+    }
+}
+
+impl StackDepth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    /// Applies a command's stack effect. Fails if the command pops more values than the
+    /// stack holds, which the generated code would not notice: it would read and overwrite
+    /// the memory below the stack. After an underflow the missing values are treated as
+    /// having been there, so one mistake is reported once rather than at every later command.
+    pub fn apply(&mut self, command: &Command) -> Result<()> {
+        let (pops, pushes) = stack_effect(command);
+        let available = self.depth;
+        self.depth = available.saturating_sub(pops) + pushes;
+        self.max_depth = self.max_depth.max(self.depth);
+
+        if pops > available {
+            return Err(anyhow!(
+                "stack underflow: {} needs {} value(s) but the stack holds {}",
+                command.name(),
+                pops,
+                available
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(program: &str) -> (StackDepth, Vec<String>) {
+        let mut stack = StackDepth::new();
+        let errors = program
+            .lines()
+            .filter_map(|line| stack.apply(&Command::from(line).unwrap()).err())
+            .map(|e| e.to_string())
+            .collect();
+        (stack, errors)
+    }
+
+    #[test]
+    fn test_depth_follows_commands() {
+        let (stack, errors) = run("push constant 1\npush constant 2\npush constant 3\n\
+            add\nneg\nlt\npush local 0\npop temp 1");
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(stack.depth(), 1);
+        assert_eq!(stack.max_depth(), 3);
+    }
+
+    #[test]
+    fn test_underflow_is_reported_once() {
+        let (stack, errors) = run("push constant 1\nadd\npush constant 2\nsub\npop local 0\nnot");
+        assert_eq!(
+            errors,
+            [
+                "stack underflow: add needs 2 value(s) but the stack holds 1",
+                "stack underflow: not needs 1 value(s) but the stack holds 0",
+            ]
+        );
+        assert_eq!(stack.depth(), 1);
+    }
+}
@@ -0,0 +1,71 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::{command::Command, stack_depth::StackDepth};
+
+/// Summary of a translated program, printed by the `stats` subcommand.
+#[derive(Debug, Default)]
+pub struct ProgramStats {
+    pub source_lines: usize,
+    pub commands: usize,
+    pub instructions: usize,
+    pub by_command: BTreeMap<String, usize>,
+    pub by_segment: BTreeMap<String, usize>,
+    stack: StackDepth,
+}
+
+impl ProgramStats {
+    pub fn record_line(&mut self) {
+        self.source_lines += 1;
+    }
+
+    pub fn record(&mut self, command: &Command, assembly: &str) {
+        self.commands += 1;
+        *self
+            .by_command
+            .entry(command.name().to_string())
+            .or_default() += 1;
+
+        if let Command::Push { segment, .. } | Command::Pop { segment, .. } = command {
+            *self.by_segment.entry(segment.to_string()).or_default() += 1;
+        }
+
+        self.instructions += count_instructions(assembly);
+        // Underflows are reported by `check`, here only the depth matters
+        let _ = self.stack.apply(command);
+    }
+
+    /// Most values the stack holds at once while the program runs.
+    pub fn max_stack_depth(&self) -> usize {
+        self.stack.max_depth()
+    }
+}
+
+/// Counts the Hack instructions in a chunk of assembly, ignoring comments, blank lines and
+/// label declarations since those do not occupy ROM.
+pub fn count_instructions(assembly: &str) -> usize {
+    assembly
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("//") && !line.starts_with('('))
+        .count()
+}
+
+impl fmt::Display for ProgramStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "source lines:      {}", self.source_lines)?;
+        writeln!(f, "vm commands:       {}", self.commands)?;
+        writeln!(f, "hack instructions: {}", self.instructions)?;
+        writeln!(f, "max stack depth:   {}", self.max_stack_depth())?;
+
+        writeln!(f, "commands:")?;
+        for (name, count) in &self.by_command {
+            writeln!(f, "  {:<8} {}", name, count)?;
+        }
+
+        writeln!(f, "segments:")?;
+        for (name, count) in &self.by_segment {
+            writeln!(f, "  {:<8} {}", name, count)?;
+        }
+        Ok(())
+    }
+}
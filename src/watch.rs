@@ -0,0 +1,322 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+
+use crate::{
+    backend::Backend,
+    cache::Fnv1a,
+    cli::Verbosity,
+    labels::check_unique_labels,
+    program::{input_files, translate_file},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Identifies a version of a file by a hash of its contents. Modification times are too
+/// coarse on some filesystems to notice an edit that keeps the length, such as two saves
+/// within the same tick.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct Stamp(u64);
+
+impl Stamp {
+    fn of(path: &Path) -> Result<Self> {
+        let content =
+            fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let mut hash = Fnv1a::new();
+        hash.write(&content);
+        Ok(Self(hash.finish()))
+    }
+}
+
+struct Unit {
+    stamp: Stamp,
+    // The translated fragment, or the diagnostic explaining why translation failed
+    fragment: Result<String, String>,
+}
+
+/// What changed during a single poll.
+pub struct Update {
+    pub retranslated: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+    pub errors: Vec<String>,
+    pub written: bool,
+}
+
+/// Polls a `.vm` file or a directory of them, retranslating only the files that changed
+/// and rebuilding the combined output from the latest fragment of every file.
+pub struct Watcher<'a> {
+    input: PathBuf,
+    output: PathBuf,
+    backend: &'a dyn Backend,
+    units: BTreeMap<PathBuf, Unit>,
+}
+
+impl<'a> Watcher<'a> {
+    pub fn new(input: PathBuf, output: PathBuf, backend: &'a dyn Backend) -> Self {
+        Self {
+            input,
+            output,
+            backend,
+            units: BTreeMap::new(),
+        }
+    }
+
+    /// Checks the input once. Returns `None` when nothing changed since the last poll.
+    pub fn poll(&mut self) -> Result<Option<Update>> {
+        let files = input_files(&self.input)?;
+
+        let removed: Vec<PathBuf> = self
+            .units
+            .keys()
+            .filter(|path| !files.contains(path))
+            .cloned()
+            .collect();
+        for path in &removed {
+            self.units.remove(path);
+        }
+
+        let mut retranslated = Vec::new();
+        for path in files {
+            let stamp = Stamp::of(&path)?;
+            if self
+                .units
+                .get(&path)
+                .is_some_and(|unit| unit.stamp == stamp)
+            {
+                continue;
+            }
+
+            let fragment = translate_file(&path, self.backend).map_err(|e| format!("{:#}", e));
+            self.units.insert(path.clone(), Unit { stamp, fragment });
+            retranslated.push(path);
+        }
+
+        if retranslated.is_empty() && removed.is_empty() {
+            return Ok(None);
+        }
+
+        let mut errors: Vec<String> = self
+            .units
+            .iter()
+            .filter_map(|(path, unit)| {
+                let error = unit.fragment.as_ref().err()?;
+                Some(format!("{}: {}", path.display(), error))
+            })
+            .collect();
+        if errors.is_empty() {
+            let names: Vec<String> = self
+                .units
+                .keys()
+                .map(|path| path.display().to_string())
+                .collect();
+            let labels = names.iter().zip(self.units.values()).map(|(name, unit)| {
+                let fragment = unit.fragment.as_deref().unwrap_or_default();
+                (name.as_str(), self.backend.defined_labels(fragment))
+            });
+            if let Err(e) = check_unique_labels(labels) {
+                errors.push(format!("{:#}", e));
+            }
+        }
+
+        // Keep the last good output around rather than replacing it with a partial program
+        let written = errors.is_empty();
+        if written {
+            let mut combined = self.backend.prologue();
+            for unit in self.units.values() {
+                combined.push_str(unit.fragment.as_deref().unwrap_or_default());
+            }
+            combined.push_str(&self.backend.epilogue());
+            fs::write(&self.output, combined)
+                .with_context(|| format!("failed to write {}", self.output.display()))?;
+        }
+
+        Ok(Some(Update {
+            retranslated,
+            removed,
+            errors,
+            written,
+        }))
+    }
+}
+
+/// Retranslates `input` into `output` whenever it changes. Only returns if the loop is
+/// interrupted, errors are reported and watching continues.
+pub fn watch(
+    input: PathBuf,
+    output: PathBuf,
+    backend: &dyn Backend,
+    verbosity: Verbosity,
+) -> Result<()> {
+    verbosity.status(format!(
+        "Watching {} for changes, press Ctrl-C to stop",
+        input.display()
+    ));
+    let mut watcher = Watcher::new(input, output, backend);
+
+    loop {
+        match watcher.poll() {
+            Ok(Some(update)) => report(&update, &watcher.output, verbosity),
+            Ok(None) => {}
+            Err(e) => eprintln!("Error: {:#}", e),
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn report(update: &Update, output: &Path, verbosity: Verbosity) {
+    for path in &update.retranslated {
+        verbosity.detail(format!("Retranslated {}", path.display()));
+    }
+    for path in &update.removed {
+        verbosity.detail(format!("Removed {}", path.display()));
+    }
+    for error in &update.errors {
+        eprintln!("{}", error);
+    }
+
+    if update.written {
+        verbosity.status(format!(
+            "Translated {} changed file(s) into {}",
+            update.retranslated.len(),
+            output.display()
+        ));
+    } else {
+        verbosity.status(format!(
+            "{} error(s), {} was not updated",
+            update.errors.len(),
+            output.display()
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        backend::{default_backend, hack::HackBackend},
+        labels::LabelScheme,
+        memory_layout::MemoryLayout,
+        test_util::TempDir,
+    };
+
+    fn setup(name: &str) -> (TempDir, Watcher<'static>) {
+        let dir = TempDir::new(&format!("watch_{}", name));
+        fs::write(dir.0.join("A.vm"), "push constant 1\n").unwrap();
+        fs::write(dir.0.join("B.vm"), "push constant 2\n").unwrap();
+        let watcher = Watcher::new(dir.0.clone(), dir.0.join("Out.asm"), default_backend());
+        (dir, watcher)
+    }
+
+    #[test]
+    fn test_only_changed_files_are_retranslated() {
+        let (dir, mut watcher) = setup("changed");
+
+        let update = watcher
+            .poll()
+            .unwrap()
+            .expect("first poll translates everything");
+        assert_eq!(update.retranslated.len(), 2);
+        assert!(update.written);
+        assert!(watcher.poll().unwrap().is_none());
+
+        fs::write(dir.0.join("B.vm"), "push constant 2\npush constant 30\n").unwrap();
+        let update = watcher.poll().unwrap().expect("change should be noticed");
+        assert_eq!(update.retranslated, vec![dir.0.join("B.vm")]);
+
+        let output = fs::read_to_string(dir.0.join("Out.asm")).unwrap();
+        assert!(output.contains("@1\n"));
+        assert!(output.contains("@30\n"));
+        assert!(output.find("@1\n") < output.find("@30\n"));
+    }
+
+    #[test]
+    fn test_edits_within_one_modification_time_are_noticed() {
+        let (dir, mut watcher) = setup("same_mtime");
+        watcher.poll().unwrap();
+
+        let path = dir.0.join("A.vm");
+        let modified = fs::metadata(&path).unwrap().modified().unwrap();
+        fs::write(&path, "push constant 7\n").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+
+        let update = watcher.poll().unwrap().expect("change should be noticed");
+        assert_eq!(update.retranslated, vec![path]);
+        assert!(
+            fs::read_to_string(dir.0.join("Out.asm"))
+                .unwrap()
+                .contains("@7\n")
+        );
+    }
+
+    #[test]
+    fn test_errors_keep_previous_output() {
+        let (dir, mut watcher) = setup("errors");
+        watcher.poll().unwrap();
+        let before = fs::read_to_string(dir.0.join("Out.asm")).unwrap();
+
+        fs::write(dir.0.join("A.vm"), "push constant 1\nbogus\n").unwrap();
+        let update = watcher.poll().unwrap().expect("change should be noticed");
+        assert!(!update.written);
+        assert_eq!(update.errors.len(), 1);
+        assert!(update.errors[0].contains("line 2: Unknown command: bogus"));
+        assert_eq!(fs::read_to_string(dir.0.join("Out.asm")).unwrap(), before);
+    }
+
+    #[test]
+    fn test_label_clashes_keep_previous_output() {
+        let dir = TempDir::new("watch_clash");
+        fs::write(dir.0.join("A.vm"), "push constant 1\n").unwrap();
+        fs::write(
+            dir.0.join("A1.vm"),
+            "push constant 1\npush constant 1\neq\n",
+        )
+        .unwrap();
+        // Under this scheme the 11th eq of A is named like the 1st eq of A1
+        let labels = LabelScheme::parse("{file}{n}{kind}").unwrap();
+        let backend = HackBackend::new(MemoryLayout::STANDARD, labels);
+        let mut watcher = Watcher::new(dir.0.clone(), dir.0.join("Out.asm"), &backend);
+        assert!(watcher.poll().unwrap().unwrap().written);
+        let before = fs::read_to_string(dir.0.join("Out.asm")).unwrap();
+
+        fs::write(
+            dir.0.join("A.vm"),
+            "push constant 1\npush constant 1\neq\n".repeat(11),
+        )
+        .unwrap();
+        let update = watcher.poll().unwrap().expect("change should be noticed");
+        assert!(!update.written);
+        assert_eq!(update.errors.len(), 1);
+        assert!(
+            update.errors[0].contains("label A11IsEqual is defined by both"),
+            "{}",
+            update.errors[0]
+        );
+        assert_eq!(fs::read_to_string(dir.0.join("Out.asm")).unwrap(), before);
+    }
+
+    #[test]
+    fn test_removed_files_are_dropped_from_output() {
+        let (dir, mut watcher) = setup("removed");
+        watcher.poll().unwrap();
+
+        fs::remove_file(dir.0.join("A.vm")).unwrap();
+        let update = watcher.poll().unwrap().expect("removal should be noticed");
+        assert_eq!(update.removed, vec![dir.0.join("A.vm")]);
+        assert!(update.retranslated.is_empty());
+
+        let output = fs::read_to_string(dir.0.join("Out.asm")).unwrap();
+        assert!(!output.contains("@1\n"));
+        assert!(output.contains("@2\n"));
+    }
+}
@@ -0,0 +1,547 @@
+// push constant 17
+@17
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// push constant 17
+@17
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// eq
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+
+// subtract both numbers
+D=M-D
+@StackTest.IsEqual.1
+D;JEQ
+@StackTest.NotEqual.1
+D;JNE
+(StackTest.IsEqual.1)
+@SP
+A=M
+M=-1
+@StackTest.EqEnd.1
+0;JMP
+(StackTest.NotEqual.1)
+@SP
+A=M
+M=0
+(StackTest.EqEnd.1)
+@SP
+M=M+1
+
+// push constant 17
+@17
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// push constant 16
+@16
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// eq
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+
+// subtract both numbers
+D=M-D
+@StackTest.IsEqual.2
+D;JEQ
+@StackTest.NotEqual.2
+D;JNE
+(StackTest.IsEqual.2)
+@SP
+A=M
+M=-1
+@StackTest.EqEnd.2
+0;JMP
+(StackTest.NotEqual.2)
+@SP
+A=M
+M=0
+(StackTest.EqEnd.2)
+@SP
+M=M+1
+
+// push constant 16
+@16
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// push constant 17
+@17
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// eq
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+
+// subtract both numbers
+D=M-D
+@StackTest.IsEqual.3
+D;JEQ
+@StackTest.NotEqual.3
+D;JNE
+(StackTest.IsEqual.3)
+@SP
+A=M
+M=-1
+@StackTest.EqEnd.3
+0;JMP
+(StackTest.NotEqual.3)
+@SP
+A=M
+M=0
+(StackTest.EqEnd.3)
+@SP
+M=M+1
+
+// push constant 892
+@892
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// push constant 891
+@891
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// lt
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+// subtract both numbers
+D=M-D
+@StackTest.IsLessThan.1
+D;JLT
+@StackTest.NotLessThan.1
+D;JGE
+(StackTest.IsLessThan.1)
+@SP
+A=M
+M=-1
+@StackTest.LtEnd.1
+0;JMP
+(StackTest.NotLessThan.1)
+@SP
+A=M
+M=0
+// increase stack pointer
+(StackTest.LtEnd.1)
+@SP
+M=M+1
+
+// push constant 891
+@891
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// push constant 892
+@892
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// lt
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+// subtract both numbers
+D=M-D
+@StackTest.IsLessThan.2
+D;JLT
+@StackTest.NotLessThan.2
+D;JGE
+(StackTest.IsLessThan.2)
+@SP
+A=M
+M=-1
+@StackTest.LtEnd.2
+0;JMP
+(StackTest.NotLessThan.2)
+@SP
+A=M
+M=0
+// increase stack pointer
+(StackTest.LtEnd.2)
+@SP
+M=M+1
+
+// push constant 891
+@891
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// push constant 891
+@891
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// lt
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+// subtract both numbers
+D=M-D
+@StackTest.IsLessThan.3
+D;JLT
+@StackTest.NotLessThan.3
+D;JGE
+(StackTest.IsLessThan.3)
+@SP
+A=M
+M=-1
+@StackTest.LtEnd.3
+0;JMP
+(StackTest.NotLessThan.3)
+@SP
+A=M
+M=0
+// increase stack pointer
+(StackTest.LtEnd.3)
+@SP
+M=M+1
+
+// push constant 32767
+@32767
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// push constant 32766
+@32766
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// gt
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+// subtract both numbers
+D=M-D
+@StackTest.IsGreaterThan.1
+D;JGT
+@StackTest.NotGreaterThan.1
+D;JLE
+(StackTest.IsGreaterThan.1)
+@SP
+A=M
+M=-1
+@StackTest.GtEnd.1
+0;JMP
+(StackTest.NotGreaterThan.1)
+@SP
+A=M
+M=0
+// increase stack pointer
+(StackTest.GtEnd.1)
+@SP
+M=M+1
+
+// push constant 32766
+@32766
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// push constant 32767
+@32767
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// gt
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+// subtract both numbers
+D=M-D
+@StackTest.IsGreaterThan.2
+D;JGT
+@StackTest.NotGreaterThan.2
+D;JLE
+(StackTest.IsGreaterThan.2)
+@SP
+A=M
+M=-1
+@StackTest.GtEnd.2
+0;JMP
+(StackTest.NotGreaterThan.2)
+@SP
+A=M
+M=0
+// increase stack pointer
+(StackTest.GtEnd.2)
+@SP
+M=M+1
+
+// push constant 32766
+@32766
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// push constant 32766
+@32766
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// gt
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+// subtract both numbers
+D=M-D
+@StackTest.IsGreaterThan.3
+D;JGT
+@StackTest.NotGreaterThan.3
+D;JLE
+(StackTest.IsGreaterThan.3)
+@SP
+A=M
+M=-1
+@StackTest.GtEnd.3
+0;JMP
+(StackTest.NotGreaterThan.3)
+@SP
+A=M
+M=0
+// increase stack pointer
+(StackTest.GtEnd.3)
+@SP
+M=M+1
+
+// push constant 57
+@57
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// push constant 31
+@31
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// push constant 53
+@53
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// add
+@SP
+M=M-1
+A=M
+D=M
+@SP
+M=M-1
+A=M
+M=D+M
+@SP
+M=M+1
+// push constant 112
+@112
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// sub
+@SP
+M=M-1
+A=M
+D=M
+@SP
+M=M-1
+A=M
+M=M-D
+@SP
+M=M+1
+// neg
+@SP
+M=M-1
+A=M
+M=-M
+@SP
+M=M+1
+// and
+@SP
+M=M-1
+A=M
+D=M
+@SP
+M=M-1
+A=M
+M=D&M
+@SP
+M=M+1
+// push constant 82
+@82
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
+
+// or
+@SP
+M=M-1
+A=M
+D=M
+@SP
+M=M-1
+A=M
+M=D|M
+@SP
+M=M+1
+// not
+@SP
+M=M-1
+A=M
+M=!M
+@SP
+M=M+1
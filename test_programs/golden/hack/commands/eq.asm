@@ -0,0 +1,31 @@
+// eq
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+
+// subtract both numbers
+D=M-D
+@Main.IsEqual.1
+D;JEQ
+@Main.NotEqual.1
+D;JNE
+(Main.IsEqual.1)
+@SP
+A=M
+M=-1
+@Main.EqEnd.1
+0;JMP
+(Main.NotEqual.1)
+@SP
+A=M
+M=0
+(Main.EqEnd.1)
+@SP
+M=M+1
@@ -0,0 +1,30 @@
+// gt
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+// subtract both numbers
+D=M-D
+@Main.IsGreaterThan.1
+D;JGT
+@Main.NotGreaterThan.1
+D;JLE
+(Main.IsGreaterThan.1)
+@SP
+A=M
+M=-1
+@Main.GtEnd.1
+0;JMP
+(Main.NotGreaterThan.1)
+@SP
+A=M
+M=0
+// increase stack pointer
+(Main.GtEnd.1)
+@SP
+M=M+1
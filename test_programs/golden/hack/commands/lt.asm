@@ -0,0 +1,30 @@
+// lt
+// load the bottom value from stack
+@SP
+M=M-1
+A=M
+D=M
+// load the second bottom value from stack
+@SP
+M=M-1
+A=M
+// subtract both numbers
+D=M-D
+@Main.IsLessThan.1
+D;JLT
+@Main.NotLessThan.1
+D;JGE
+(Main.IsLessThan.1)
+@SP
+A=M
+M=-1
+@Main.LtEnd.1
+0;JMP
+(Main.NotLessThan.1)
+@SP
+A=M
+M=0
+// increase stack pointer
+(Main.LtEnd.1)
+@SP
+M=M+1
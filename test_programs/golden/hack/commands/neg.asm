@@ -0,0 +1,7 @@
+// neg
+@SP
+M=M-1
+A=M
+M=-M
+@SP
+M=M+1
\ No newline at end of file
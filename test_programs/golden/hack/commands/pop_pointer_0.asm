@@ -0,0 +1,7 @@
+// pop pointer 0
+@SP
+M=M-1
+A=M
+D=M
+@THIS
+M=D
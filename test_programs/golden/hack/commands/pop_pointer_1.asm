@@ -0,0 +1,7 @@
+// pop pointer 1
+@SP
+M=M-1
+A=M
+D=M
+@THAT
+M=D
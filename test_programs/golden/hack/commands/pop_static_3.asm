@@ -0,0 +1,7 @@
+// pop static 3
+@SP
+M=M-1
+A=M
+D=M
+@Main.3
+M=D
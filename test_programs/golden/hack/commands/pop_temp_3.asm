@@ -0,0 +1,18 @@
+// pop temp 3
+@SP
+M=M-1
+A=M
+D=M
+@Main.tmp.1
+M=D
+@5 // base address for temp
+D=A
+@3
+D=D+A
+@Main.tmp2.1
+M=D
+@Main.tmp.1
+D=M
+@Main.tmp2.1
+A=M
+M=D
@@ -0,0 +1,19 @@
+// pop that 3
+@SP
+                        M=M-1
+                        A=M
+                        D=M
+                        @Main.tmp.1
+                        M=D
+                        @THAT
+                        D=M
+                        @3
+                        D=D+A
+                        @Main.tmp2.1
+                        M=D
+                        @Main.tmp.1
+                        D=M
+                        @Main.tmp2.1
+                        A=M
+                        M=D
+                        
\ No newline at end of file
@@ -0,0 +1,12 @@
+// push argument 3
+@ARG
+D=M
+@3
+D=D+A
+A=D
+D=M
+@SP
+A=M
+M=D
+@SP
+M=M+1
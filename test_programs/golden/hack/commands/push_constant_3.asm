@@ -0,0 +1,8 @@
+// push constant 3
+@3
+D=A
+@SP
+A=M
+M=D
+@SP
+M=M+1
@@ -0,0 +1,12 @@
+// push local 3
+@LCL
+D=M
+@3
+D=D+A
+A=D
+D=M
+@SP
+A=M
+M=D
+@SP
+M=M+1
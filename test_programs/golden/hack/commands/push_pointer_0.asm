@@ -0,0 +1,8 @@
+// push pointer 0
+@THIS
+D=M
+@SP
+A=M
+M=D
+@SP
+M=M+1
@@ -0,0 +1,9 @@
+// push static 3
+@Main.3
+D=M
+@SP
+A=M
+M=D
+@SP
+M=M+1
+        
\ No newline at end of file
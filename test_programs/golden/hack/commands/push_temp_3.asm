@@ -0,0 +1,12 @@
+// push temp 3
+@5
+D=A
+@3
+D=D+A
+A=D
+D=M
+@SP
+A=M
+M=D
+@SP
+M=M+1
@@ -0,0 +1,12 @@
+// push that 3
+@THAT
+D=M
+@3
+D=D+A
+A=D
+D=M
+@SP
+A=M
+M=D
+@SP
+M=M+1
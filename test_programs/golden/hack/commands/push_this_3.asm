@@ -0,0 +1,12 @@
+// push this 3
+@THIS
+D=M
+@3
+D=D+A
+A=D
+D=M
+@SP
+A=M
+M=D
+@SP
+M=M+1
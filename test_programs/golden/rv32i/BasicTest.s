@@ -0,0 +1,353 @@
+# Generated by vm_translator
+    .data
+    .globl vm_ram
+    .balign 4
+vm_ram:
+    .half 256
+    .zero 65534
+
+    .text
+    .globl vm_run
+vm_run:
+    addi sp, sp, -16
+    sw s0, 12(sp)
+    la s0, vm_ram
+
+    # push constant 10
+    li t0, 10
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # pop local 0
+    lhu t3, 2(s0)
+    addi t3, t3, 0
+    slli t3, t3, 1
+    add t3, s0, t3
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 0(t3)
+
+    # push constant 21
+    li t0, 21
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 22
+    li t0, 22
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # pop argument 2
+    lhu t3, 4(s0)
+    addi t3, t3, 2
+    slli t3, t3, 1
+    add t3, s0, t3
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 0(t3)
+
+    # pop argument 1
+    lhu t3, 4(s0)
+    addi t3, t3, 1
+    slli t3, t3, 1
+    add t3, s0, t3
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 0(t3)
+
+    # push constant 36
+    li t0, 36
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # pop this 6
+    lhu t3, 6(s0)
+    addi t3, t3, 6
+    slli t3, t3, 1
+    add t3, s0, t3
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 0(t3)
+
+    # push constant 42
+    li t0, 42
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 45
+    li t0, 45
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # pop that 5
+    lhu t3, 8(s0)
+    addi t3, t3, 5
+    slli t3, t3, 1
+    add t3, s0, t3
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 0(t3)
+
+    # pop that 2
+    lhu t3, 8(s0)
+    addi t3, t3, 2
+    slli t3, t3, 1
+    add t3, s0, t3
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 0(t3)
+
+    # push constant 510
+    li t0, 510
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # pop temp 6
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 22(s0)
+
+    # push local 0
+    lhu t3, 2(s0)
+    addi t3, t3, 0
+    slli t3, t3, 1
+    add t3, s0, t3
+    lh t0, 0(t3)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push that 5
+    lhu t3, 8(s0)
+    addi t3, t3, 5
+    slli t3, t3, 1
+    add t3, s0, t3
+    lh t0, 0(t3)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # add
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    add t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push argument 1
+    lhu t3, 4(s0)
+    addi t3, t3, 1
+    slli t3, t3, 1
+    add t3, s0, t3
+    lh t0, 0(t3)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # sub
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sub t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push this 6
+    lhu t3, 6(s0)
+    addi t3, t3, 6
+    slli t3, t3, 1
+    add t3, s0, t3
+    lh t0, 0(t3)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push this 6
+    lhu t3, 6(s0)
+    addi t3, t3, 6
+    slli t3, t3, 1
+    add t3, s0, t3
+    lh t0, 0(t3)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # add
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    add t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # sub
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sub t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push temp 6
+    lh t0, 22(s0)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # add
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    add t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+
+    lw s0, 12(sp)
+    addi sp, sp, 16
+    ret
@@ -0,0 +1,209 @@
+# Generated by vm_translator
+    .data
+    .globl vm_ram
+    .balign 4
+vm_ram:
+    .half 256
+    .zero 65534
+
+    .text
+    .globl vm_run
+vm_run:
+    addi sp, sp, -16
+    sw s0, 12(sp)
+    la s0, vm_ram
+
+    # push constant 3030
+    li t0, 3030
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # pop pointer 0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 6(s0)
+
+    # push constant 3040
+    li t0, 3040
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # pop pointer 1
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 8(s0)
+
+    # push constant 32
+    li t0, 32
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # pop this 2
+    lhu t3, 6(s0)
+    addi t3, t3, 2
+    slli t3, t3, 1
+    add t3, s0, t3
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 0(t3)
+
+    # push constant 46
+    li t0, 46
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # pop that 6
+    lhu t3, 8(s0)
+    addi t3, t3, 6
+    slli t3, t3, 1
+    add t3, s0, t3
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 0(t3)
+
+    # push pointer 0
+    lh t0, 6(s0)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push pointer 1
+    lh t0, 8(s0)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # add
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    add t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push this 2
+    lhu t3, 6(s0)
+    addi t3, t3, 2
+    slli t3, t3, 1
+    add t3, s0, t3
+    lh t0, 0(t3)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # sub
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sub t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push that 6
+    lhu t3, 8(s0)
+    addi t3, t3, 6
+    slli t3, t3, 1
+    add t3, s0, t3
+    lh t0, 0(t3)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # add
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    add t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+
+    lw s0, 12(sp)
+    addi sp, sp, 16
+    ret
@@ -0,0 +1,59 @@
+# Generated by vm_translator
+    .data
+    .globl vm_ram
+    .balign 4
+vm_ram:
+    .half 256
+    .zero 65534
+
+    .text
+    .globl vm_run
+vm_run:
+    addi sp, sp, -16
+    sw s0, 12(sp)
+    la s0, vm_ram
+
+    # push constant 7
+    li t0, 7
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 8
+    li t0, 8
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # sub
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sub t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+
+    lw s0, 12(sp)
+    addi sp, sp, 16
+    ret
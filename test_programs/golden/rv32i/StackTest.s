@@ -0,0 +1,554 @@
+# Generated by vm_translator
+    .data
+    .globl vm_ram
+    .balign 4
+vm_ram:
+    .half 256
+    .zero 65534
+
+    .text
+    .globl vm_run
+vm_run:
+    addi sp, sp, -16
+    sw s0, 12(sp)
+    la s0, vm_ram
+
+    # push constant 17
+    li t0, 17
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 17
+    li t0, 17
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # eq
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sub t0, t0, t4
+    seqz t0, t0
+    neg t0, t0
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 17
+    li t0, 17
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 16
+    li t0, 16
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # eq
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sub t0, t0, t4
+    seqz t0, t0
+    neg t0, t0
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 16
+    li t0, 16
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 17
+    li t0, 17
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # eq
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sub t0, t0, t4
+    seqz t0, t0
+    neg t0, t0
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 892
+    li t0, 892
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 891
+    li t0, 891
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # lt
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    slt t0, t0, t4
+    neg t0, t0
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 891
+    li t0, 891
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 892
+    li t0, 892
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # lt
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    slt t0, t0, t4
+    neg t0, t0
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 891
+    li t0, 891
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 891
+    li t0, 891
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # lt
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    slt t0, t0, t4
+    neg t0, t0
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 32767
+    li t0, 32767
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 32766
+    li t0, 32766
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # gt
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    slt t0, t4, t0
+    neg t0, t0
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 32766
+    li t0, 32766
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 32767
+    li t0, 32767
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # gt
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    slt t0, t4, t0
+    neg t0, t0
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 32766
+    li t0, 32766
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 32766
+    li t0, 32766
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # gt
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    slt t0, t4, t0
+    neg t0, t0
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 57
+    li t0, 57
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 31
+    li t0, 31
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 53
+    li t0, 53
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # add
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    add t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 112
+    li t0, 112
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # sub
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sub t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # neg
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    neg t0, t0
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # and
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    and t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 82
+    li t0, 82
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # or
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    or t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # not
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    not t0, t0
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+
+    lw s0, 12(sp)
+    addi sp, sp, 16
+    ret
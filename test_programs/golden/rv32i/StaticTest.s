@@ -0,0 +1,165 @@
+# Generated by vm_translator
+    .data
+    .globl vm_ram
+    .balign 4
+vm_ram:
+    .half 256
+    .zero 65534
+
+    .text
+    .globl vm_run
+vm_run:
+    addi sp, sp, -16
+    sw s0, 12(sp)
+    la s0, vm_ram
+
+    # push constant 111
+    li t0, 111
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 333
+    li t0, 333
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push constant 888
+    li t0, 888
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    .pushsection .bss
+    .balign 2
+vm_static.StaticTest.8:
+    .zero 2
+    .popsection
+    # pop static 8
+    la t3, vm_static.StaticTest.8
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 0(t3)
+
+    .pushsection .bss
+    .balign 2
+vm_static.StaticTest.3:
+    .zero 2
+    .popsection
+    # pop static 3
+    la t3, vm_static.StaticTest.3
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 0(t3)
+
+    .pushsection .bss
+    .balign 2
+vm_static.StaticTest.1:
+    .zero 2
+    .popsection
+    # pop static 1
+    la t3, vm_static.StaticTest.1
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sh t0, 0(t3)
+
+    # push static 3
+    la t3, vm_static.StaticTest.3
+    lh t0, 0(t3)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push static 1
+    la t3, vm_static.StaticTest.1
+    lh t0, 0(t3)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # sub
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    sub t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # push static 8
+    la t3, vm_static.StaticTest.8
+    lh t0, 0(t3)
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+    # add
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    mv t4, t0
+    lhu t1, 0(s0)
+    addi t1, t1, -1
+    sh t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    lh t0, 0(t2)
+    add t0, t0, t4
+    lhu t1, 0(s0)
+    slli t2, t1, 1
+    add t2, s0, t2
+    sh t0, 0(t2)
+    addi t1, t1, 1
+    sh t1, 0(s0)
+
+
+    lw s0, 12(sp)
+    addi sp, sp, 16
+    ret